@@ -6,8 +6,11 @@ use rand::{
 };
 use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+/// The seven tetrominoes. See [`BlockShape::letter`] for their usual names.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
 pub enum BlockShape {
     Square,
@@ -21,7 +24,7 @@ pub enum BlockShape {
 
 impl Distribution<BlockShape> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> BlockShape {
-        match rng.gen_range(0..6) {
+        match rng.gen_range(0..7) {
             // rand 0.8
             0 => BlockShape::Square,
             1 => BlockShape::Line,
@@ -35,13 +38,69 @@ impl Distribution<BlockShape> for Standard {
 }
 
 impl BlockShape {
+    /// A shape drawn from the thread's random generator.
     pub fn get_rand() -> BlockShape {
         let shape: BlockShape = rand::random();
         shape
     }
+
+    /// The usual one-letter name of the shape: O, I, T, L, J, S or Z. `Z` is
+    /// the S piece and `ZRev` the Z piece.
+    pub fn letter(&self) -> char {
+        match self {
+            BlockShape::Square => 'O',
+            BlockShape::Line => 'I',
+            BlockShape::T => 'T',
+            BlockShape::L => 'L',
+            BlockShape::LRev => 'J',
+            BlockShape::Z => 'S',
+            BlockShape::ZRev => 'Z',
+        }
+    }
+
+    /// The shape named by `letter`, in either case.
+    pub fn from_letter(letter: char) -> Option<BlockShape> {
+        BlockShape::iter().find(|shape| shape.letter() == letter.to_ascii_uppercase())
+    }
+}
+
+/// Shapes are named as in the source (`Square`, `LRev`, ...), the form saves
+/// store.
+impl fmt::Display for BlockShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BlockShape::Square => "Square",
+            BlockShape::Line => "Line",
+            BlockShape::T => "T",
+            BlockShape::L => "L",
+            BlockShape::LRev => "LRev",
+            BlockShape::Z => "Z",
+            BlockShape::ZRev => "ZRev",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BlockShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(BlockShape::Square),
+            "line" => Ok(BlockShape::Line),
+            "t" => Ok(BlockShape::T),
+            "l" => Ok(BlockShape::L),
+            "lrev" => Ok(BlockShape::LRev),
+            "z" => Ok(BlockShape::Z),
+            "zrev" => Ok(BlockShape::ZRev),
+            _ => Err(format!("unknown shape '{}'", s)),
+        }
+    }
 }
 
-#[derive(Clone)]
+/// A tetromino placed somewhere: its shape and the four cells it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub shape: BlockShape,
     pub coordinates: ArrayVec<Point, 4>,
@@ -61,6 +120,8 @@ impl fmt::Display for BlockMoveError {
 impl std::error::Error for BlockMoveError {}
 
 impl Block {
+    /// A block of `shape` in its spawn orientation, with the top left corner
+    /// of its bounding box at `origin`.
     pub fn new(origin: Point, shape: BlockShape) -> Block {
         // Define relative coordinates for each BlockShape
         let relative_coordinates = match shape {
@@ -77,7 +138,7 @@ impl Block {
                 Point::new(3, 0),
             ],
             BlockShape::T => [
-                Point::new(0, 0),
+                Point::new(1, 0),
                 Point::new(0, 1),
                 Point::new(1, 1),
                 Point::new(2, 1),
@@ -115,6 +176,9 @@ impl Block {
         Block { shape, coordinates }
     }
 
+    /// The block moved by (`dx`, `dy`), or `None` if that leaves the sides or
+    /// top of a board of the given size, or goes more than a row past the
+    /// bottom.
     pub fn translate(
         &self,
         dx: i32,
@@ -127,7 +191,7 @@ impl Block {
             .iter()
             .map(|point| Point::new(point.get_x() + dx, point.get_y() + dy))
             .collect();
-    
+
         // Ensure all points are within bounds
         if translated_coordinates.iter().all(|point| {
             point.get_x() >= 0
@@ -143,7 +207,6 @@ impl Block {
             None
         }
     }
-    
 
     /// Rotates the block 90 degrees clockwise around its geometric center
     pub fn rotate(&self) -> Result<Block, Box<dyn Error>> {
@@ -190,6 +253,7 @@ impl Block {
         }
     }
 
+    /// Whether every cell is right of the left edge and below the top edge.
     pub fn not_negative(&self) -> bool {
         self.coordinates.iter().all(|f| f.not_negative())
     }
@@ -246,6 +310,39 @@ mod tests {
         assert_eq!(block.coordinates.as_slice(), &expected_coordinates);
     }
 
+    #[test]
+    fn test_t_block_initialization() {
+        let origin = Point::new(3, 0);
+        let block = Block::new(origin, BlockShape::T);
+
+        // The nub sits over the middle of the three cells below it
+        let expected_coordinates = [
+            Point::new(4, 0),
+            Point::new(3, 1),
+            Point::new(4, 1),
+            Point::new(5, 1),
+        ];
+        assert_eq!(block.coordinates.as_slice(), &expected_coordinates);
+    }
+
+    #[test]
+    fn test_shape_letters() {
+        for shape in BlockShape::iter() {
+            assert_eq!(BlockShape::from_letter(shape.letter()), Some(shape));
+        }
+        assert_eq!(BlockShape::from_letter('j'), Some(BlockShape::LRev));
+        assert_eq!(BlockShape::from_letter('#'), None);
+    }
+
+    #[test]
+    fn test_shape_names() {
+        for shape in BlockShape::iter() {
+            assert_eq!(shape.to_string().parse(), Ok(shape));
+        }
+        assert_eq!(BlockShape::LRev.to_string(), "LRev");
+        assert!("J".parse::<BlockShape>().is_err());
+    }
+
     #[test]
     fn test_block_rotation_square() {
         let origin = Point::new(4, 4);
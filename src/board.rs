@@ -1,10 +1,25 @@
-use crate::block::Block;
+use crate::block::{Block, BlockShape};
 use crate::point::Point;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// What occupies a filled cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    /// Part of a block that was placed on the board.
+    Block(BlockShape),
+    /// Garbage pushed in from the bottom, or any cell not placed by a block.
+    Garbage,
+}
 
+/// The playfield: the locked cells of a board `x_dim` wide and `y_dim` high.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
     pub x_dim: i32,
     pub y_dim: i32,
-    pub filled: Vec<Point>,
+    /// Every locked cell and what fills it.
+    pub cells: HashMap<Point, Cell>,
 }
 
 impl Board {
@@ -12,18 +27,29 @@ impl Board {
         Board {
             x_dim: x_dim as i32,
             y_dim: y_dim as i32,
-            filled: Vec::new(),
+            cells: HashMap::new(),
         }
     }
 
-    pub fn sort_filled(&mut self) {
-        self.filled.sort();
+    /// The content of the cell at `point`, or `None` if it is empty.
+    pub fn cell(&self, point: &Point) -> Option<Cell> {
+        self.cells.get(point).copied()
+    }
+
+    /// Every locked cell sorted by row, then column, for output that
+    /// mustn't depend on the map's order.
+    pub fn sorted_cells(&self) -> Vec<(Point, Cell)> {
+        let mut cells: Vec<_> = self.cells.iter().map(|(p, c)| (*p, *c)).collect();
+        cells.sort_by_key(|(p, _)| (p.get_y(), p.get_x()));
+        cells
     }
 
-    pub fn clear_board(&mut self) -> i32{
+    /// Removes full rows, drops the rows above them and returns how many
+    /// were cleared.
+    pub fn clear_board(&mut self) -> i32 {
         // Identify rows that need to be cleared
         let mut row_counts = vec![0; self.y_dim as usize];
-        for point in &self.filled {
+        for point in self.cells.keys() {
             if point.get_y() >= 0 && point.get_y() < self.y_dim {
                 row_counts[point.get_y() as usize] += 1;
             }
@@ -38,54 +64,159 @@ impl Board {
             .collect();
 
         if completed_rows.is_empty() {
-            return 0
+            return 0;
         }
 
-        // Remove points in the completed rows
-        self.filled.retain(|point| !completed_rows.contains(&point.get_y()));
+        // Remove cells in the completed rows and shift the rest down by one
+        // for each cleared row below them
+        self.cells = self
+            .cells
+            .drain()
+            .filter(|(point, _)| !completed_rows.contains(&point.get_y()))
+            .map(|(point, cell)| {
+                let drop = completed_rows
+                    .iter()
+                    .filter(|&&y| y > point.get_y())
+                    .count();
+                (Point::new(point.get_x(), point.get_y() + drop as i32), cell)
+            })
+            .collect();
+
+        completed_rows.len() as i32
+    }
+
+    /// Whether `block` overlaps a locked cell or reaches below the floor, so
+    /// it can't be there. The sides are checked when blocks move.
+    pub fn block_touches(&self, block: &Block) -> bool {
+        block
+            .coordinates
+            .iter()
+            .any(|point| point.get_y() >= self.y_dim || self.cells.contains_key(point))
+    }
 
-        // Apply gravity: shift points down by one for each cleared row below them
-        for cleared_row in completed_rows.iter() {
-            self.filled.iter_mut().for_each(|point| {
-                if point.get_y() < *cleared_row {
-                    *point = Point::new(point.get_x(), point.get_y() + 1);
-                }
-            });
+    /// Places a block on the board, filling its cells with its shape.
+    pub fn place_block(&mut self, block: &Block) {
+        for point in &block.coordinates {
+            self.cells.insert(*point, Cell::Block(block.shape));
         }
+    }
 
-        // Sort filled points to maintain consistent rendering
-        self.filled.sort_by_key(|p| (p.get_y(), p.get_x()));
+    /// Pushes a garbage row in from the bottom with the columns in `holes`
+    /// left empty, shifting every existing cell up one row.
+    pub fn insert_garbage_row(&mut self, holes: &[i32]) {
+        self.cells = self
+            .cells
+            .drain()
+            .map(|(point, cell)| (Point::new(point.get_x(), point.get_y() - 1), cell))
+            .collect();
 
-        completed_rows.len() as i32
+        let bottom = self.y_dim - 1;
+        for x in (0..self.x_dim).filter(|x| !holes.contains(x)) {
+            self.cells.insert(Point::new(x, bottom), Cell::Garbage);
+        }
     }
 
-    pub fn block_touches(&self, block: &Block) -> bool {
-        // Check for overlapping with filled cells or the bottom of the board
-        for block_point in &block.coordinates {
-            if block_point.get_y() >= self.y_dim {
-                return true; // Touching the bottom of the board
+    /// Builds a `width` x `height` board from rows of text, top row first,
+    /// with the last row at the bottom of the board. `.` is an empty cell, a
+    /// shape letter (see [`BlockShape::letter`]) a cell placed by that shape
+    /// and `#` or `X` garbage.
+    pub fn from_rows(width: usize, height: usize, rows: &[&str]) -> Result<Board, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("a board can't be {}x{}", width, height));
+        }
+        if rows.len() > height {
+            return Err(format!(
+                "{} rows do not fit a board {} high",
+                rows.len(),
+                height
+            ));
+        }
+        let mut board = Board::new(width, height);
+        let top = (height - rows.len()) as i32;
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row '{}' is not {} cells wide", row, width));
             }
-            for filled_point in &self.filled {
-                if block_point.get_x() == filled_point.get_x()
-                    && block_point.get_y() == filled_point.get_y()
-                {
-                    return true; // Overlapping with filled points
-                }
+            for (x, c) in row.chars().enumerate() {
+                let cell = match c {
+                    '.' => continue,
+                    '#' | 'X' => Cell::Garbage,
+                    c => Cell::Block(
+                        BlockShape::from_letter(c)
+                            .ok_or_else(|| format!("unknown cell '{}'", c))?,
+                    ),
+                };
+                board
+                    .cells
+                    .insert(Point::new(x as i32, top + y as i32), cell);
             }
         }
-        false
+        Ok(board)
     }
-    
 
-    /// Places a block on the board by adding its points to the filled vector
-    pub fn place_block(&mut self, block: &Block) {
-        for point in &block.coordinates {
-            // Add each block's point to the filled vector
-            self.filled.push(Point::new(point.get_x(), point.get_y()));
+    /// Every row as text, top row first, in the form read by [`Board::from_rows`].
+    /// The board's `Display` form is these rows on separate lines.
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.y_dim)
+            .map(|y| {
+                (0..self.x_dim)
+                    .map(|x| match self.cell(&Point::new(x, y)) {
+                        Some(Cell::Block(shape)) => shape.letter(),
+                        Some(Cell::Garbage) => '#',
+                        None => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether cells have been pushed above the top row.
+    pub fn overflowed(&self) -> bool {
+        self.cells.keys().any(|point| point.get_y() < 0)
+    }
+
+    /// Number of rows holding at least one garbage cell.
+    pub fn garbage_rows(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|(_, cell)| **cell == Cell::Garbage)
+            .map(|(point, _)| point.get_y())
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+}
+
+/// Prints the board as rows of cells, one line per row, top row first.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.rows() {
+            writeln!(f, "{}", row)?;
         }
+        Ok(())
+    }
+}
 
-        // Sort the filled points for easier processing (e.g., clearing rows)
-        self.sort_filled();
+/// Reads a board printed by `Display`: one line of cells per row, as in
+/// [`Board::from_rows`]. The board is as wide as the rows and as high as the
+/// number of rows. Surrounding whitespace and blank lines are ignored, so
+/// boards can be written as indented ASCII art:
+///
+/// ```text
+/// ....
+/// .T..
+/// TTT#
+/// ```
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = s
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        Board::from_rows(width, rows.len(), &rows)
     }
 }
 
@@ -93,74 +224,92 @@ impl Board {
 mod board_tests {
     use super::*;
 
+    /// Reads a board written as ASCII art in a test.
+    pub(super) fn parse_board(text: &str) -> Board {
+        text.parse().unwrap()
+    }
+
     #[test]
     fn test_clear_no_rows_filled() {
-        let mut board = Board::new(4, 5);
-        board.filled = vec![Point::new(0, 0), Point::new(1, 2), Point::new(3, 4)];
+        let text = "
+            #...
+            ....
+            .#..
+            ....
+            ...#";
+        let mut board = parse_board(text);
 
-        board.clear_board();
+        assert_eq!(board.clear_board(), 0);
 
         // No rows were cleared, so the filled points remain unchanged
-        let expected = vec![Point::new(0, 0), Point::new(1, 2), Point::new(3, 4)];
-        assert_eq!(board.filled, expected);
+        assert_eq!(board, parse_board(text));
     }
 
     #[test]
     fn test_clear_one_row_filled() {
-        let mut board = Board::new(4, 5);
-        board.filled = vec![
-            Point::new(0, 0),
-            Point::new(1, 0),
-            Point::new(2, 0),
-            Point::new(3, 0), // Row 0 is full
-            Point::new(0, 1),
-            Point::new(1, 2),
-            Point::new(2, 2),
-        ];
-
-        board.clear_board();
-
-        // Row 0 is cleared, and the points above it shift down
-        let expected = vec![Point::new(0, 0), Point::new(1, 1), Point::new(2, 1)];
-        assert_eq!(board.filled, expected);
+        let mut board = parse_board(
+            "
+            ....
+            ....
+            .##.
+            #...
+            ####",
+        );
+
+        assert_eq!(board.clear_board(), 1);
+
+        // The bottom row is cleared, and the cells above it shift down
+        let expected = "
+            ....
+            ....
+            ....
+            .##.
+            #...";
+        assert_eq!(board, parse_board(expected));
     }
 
     #[test]
     fn test_clear_multiple_rows_filled() {
-        let mut board = Board::new(4, 5);
-        board.filled = vec![
-            Point::new(0, 0),
-            Point::new(1, 0),
-            Point::new(2, 0),
-            Point::new(3, 0), // Row 0 is full
-            Point::new(0, 1),
-            Point::new(1, 1),
-            Point::new(2, 1),
-            Point::new(3, 1), // Row 1 is full
-            Point::new(0, 2),
-            Point::new(1, 2),
-            Point::new(2, 3),
-        ];
-
-        board.clear_board();
-
-        // Rows 0 and 1 are cleared, and points above them shift down
-        let expected = vec![Point::new(0, 0), Point::new(1, 0), Point::new(2, 1)];
-        assert_eq!(board.filled, expected);
+        let mut board = parse_board(
+            "
+            ....
+            ..#.
+            ##..
+            ####
+            ####",
+        );
+
+        assert_eq!(board.clear_board(), 2);
+
+        // The bottom two rows are cleared, and the cells above them shift down
+        let expected = "
+            ....
+            ....
+            ....
+            ..#.
+            ##..";
+        assert_eq!(board, parse_board(expected));
     }
 }
 
 #[cfg(test)]
 mod board_block_tests {
+    use super::board_tests::parse_board;
     use super::*;
     use crate::block::BlockShape;
 
     #[test]
     fn test_block_touches_filled() {
-        let mut board = Board::new(4, 5);
-        board.filled = vec![Point::new(1, 3), Point::new(2, 3)];
+        let board = parse_board(
+            "
+            ....
+            ....
+            ....
+            .##.
+            ....",
+        );
 
-        let block = Block::new(Point::new(1, 2), BlockShape::Line);
+        let block = Block::new(Point::new(1, 3), BlockShape::Line);
 
         // Block touches the filled points (overlapping)
         assert!(board.block_touches(&block));
@@ -181,6 +330,43 @@ mod board_block_tests {
         assert!(board.block_touches(&block));
     }
 
+    #[test]
+    fn test_insert_garbage_row() {
+        let mut board = Board::new(4, 5);
+        board.place_block(&Block::new(Point::new(0, 3), BlockShape::Square));
+
+        board.insert_garbage_row(&[2]);
+
+        // The square moved up a row and the new bottom row has a hole at x = 2
+        let expected = "
+            ....
+            ....
+            OO..
+            OO..
+            ##.#";
+        assert_eq!(board, parse_board(expected));
+        assert_eq!(board.cell(&Point::new(0, 4)), Some(Cell::Garbage));
+        assert_eq!(board.garbage_rows(), 1);
+        assert!(!board.overflowed());
+    }
+
+    #[test]
+    fn test_clear_moves_shapes() {
+        let mut board = Board::new(4, 5);
+        board.insert_garbage_row(&[0, 1]);
+        board.place_block(&Block::new(Point::new(0, 3), BlockShape::Square));
+
+        assert_eq!(board.clear_board(), 1);
+        assert_eq!(board.garbage_rows(), 0);
+        let expected = "
+            ....
+            ....
+            ....
+            ....
+            OO..";
+        assert_eq!(board, parse_board(expected));
+    }
+
     #[test]
     fn test_place_block() {
         let mut board = Board::new(4, 5);
@@ -189,16 +375,52 @@ mod board_block_tests {
 
         board.place_block(&block);
 
-        let mut expected_filled = vec![
-            Point::new(0, 0),
-            Point::new(1, 0),
-            Point::new(0, 1),
-            Point::new(1, 1),
-        ];
+        let expected = "
+            OO..
+            OO..
+            ....
+            ....
+            ....";
+        assert_eq!(board, parse_board(expected));
+    }
 
-        board.sort_filled();
-        expected_filled.sort_by_key(|p| (p.get_y(), p.get_x())); // Sort by y, then x
+    #[test]
+    fn test_rows_round_trip() {
+        let board = Board::from_rows(4, 5, &["T...", "TTO#", "TXOO"]).unwrap();
+
+        assert_eq!(
+            board.cell(&Point::new(0, 2)),
+            Some(Cell::Block(BlockShape::T))
+        );
+        assert_eq!(board.cell(&Point::new(3, 3)), Some(Cell::Garbage));
+        assert_eq!(board.cell(&Point::new(1, 4)), Some(Cell::Garbage));
+        assert_eq!(board.cells.len(), 9);
+        assert_eq!(board.rows(), ["....", "....", "T...", "TTO#", "T#OO"]);
+        assert_eq!(
+            Board::from_rows(4, 5, &["....", "....", "T...", "TTO#", "T#OO"]).unwrap(),
+            board
+        );
+
+        assert!(Board::from_rows(4, 5, &["..."]).is_err());
+        assert!(Board::from_rows(4, 5, &["..?."]).is_err());
+        assert!(Board::from_rows(4, 1, &["....", "...."]).is_err());
+    }
 
-        assert_eq!(board.filled, expected_filled);
+    #[test]
+    fn test_text_round_trip() {
+        let text = "....\n.T..\nTTTZ\n##ZZ\n#.#Z\n";
+        let board: Board = text.parse().unwrap();
+
+        assert_eq!((board.x_dim, board.y_dim), (4, 5));
+        assert_eq!(board.to_string(), text);
+        assert_eq!(
+            board.cell(&Point::new(3, 2)),
+            Some(Cell::Block(BlockShape::ZRev))
+        );
+        assert!("...\n....".parse::<Board>().is_err());
+        assert_eq!("".parse::<Board>(), Err("a board can't be 0x0".to_string()));
+        assert!("\n  \n".parse::<Board>().is_err());
+        assert!(Board::from_rows(0, 5, &[]).is_err());
+        assert!(Board::from_rows(4, 0, &[]).is_err());
     }
 }
@@ -0,0 +1,361 @@
+use crate::block::Block;
+use crate::board::Board;
+use crate::game::{Action, Game};
+use crate::point::Point;
+use crate::search::routes;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How much each feature of a board counts when the bot compares
+/// placements. Height, holes and bumpiness are bad, so their weights are
+/// negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    /// Per cell of the summed column heights.
+    pub height: f64,
+    /// Per line cleared by the placement.
+    pub lines: f64,
+    /// Per empty cell with a filled cell somewhere above it.
+    pub holes: f64,
+    /// Per cell of height difference between neighbouring columns.
+    pub bumpiness: f64,
+}
+
+impl Default for Weights {
+    /// Weights known to play well in endless games.
+    fn default() -> Self {
+        Weights {
+            height: -0.510066,
+            lines: 0.760666,
+            holes: -0.35663,
+            bumpiness: -0.184483,
+        }
+    }
+}
+
+impl Weights {
+    /// Reads a weights file, as written by [`Weights::save`].
+    pub fn load(path: &Path) -> Result<Weights, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read weights {}: {}", path.display(), e))?;
+        Ok(text.parse()?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, format!("# Bot evaluation weights\n{}", self))
+            .map_err(|e| format!("cannot write weights {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Sets a single weight by name.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let weight = match key {
+            "height" => &mut self.height,
+            "lines" => &mut self.lines,
+            "holes" => &mut self.holes,
+            "bumpiness" => &mut self.bumpiness,
+            _ => return Err(format!("unknown weight '{}'", key)),
+        };
+        *weight = value
+            .parse()
+            .map_err(|_| format!("invalid value '{}' for {}", value, key))?;
+        Ok(())
+    }
+}
+
+/// Formats as `key = value` lines, the form of a weights file.
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "height = {}", self.height)?;
+        writeln!(f, "lines = {}", self.lines)?;
+        writeln!(f, "holes = {}", self.holes)?;
+        writeln!(f, "bumpiness = {}", self.bumpiness)
+    }
+}
+
+/// Parses `key = value` lines like a config file; missing weights keep
+/// their defaults.
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Weights::default();
+        for (index, raw) in s.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                format!("line {}: expected 'key = value', got '{}'", index + 1, line)
+            })?;
+            weights
+                .set(key.trim(), value.trim())
+                .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        }
+        Ok(weights)
+    }
+}
+
+/// A spot the current block can lock in, the inputs that take it
+/// there and how good the board looks afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub block: Block,
+    /// Moves ending with a hard drop.
+    pub inputs: Vec<Action>,
+    pub score: f64,
+}
+
+/// Decides where each piece goes: the built-in [`Bot`], or an external bot
+/// over the Tetris Bot Protocol (see `tbp::Client`).
+pub trait Player {
+    /// The inputs for the falling block, asked for as it appears.
+    fn plan(&mut self, game: &Game) -> Result<Vec<Action>, Box<dyn Error>>;
+}
+
+/// A player that tries every placement of the current block it can reach,
+/// tucks and spins included, and takes the one leaving the best-looking
+/// board.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bot {
+    pub weights: Weights,
+}
+
+impl Bot {
+    /// The best placement for the falling block, or `None` between pieces
+    /// and once the game has ended.
+    pub fn choose(&self, game: &Game) -> Option<Placement> {
+        if game.ended() {
+            return None;
+        }
+        self.best(
+            &game.board,
+            game.active_block()?,
+            game.gravity().is_instant(),
+        )
+    }
+
+    /// The best placement `block` can reach on `board`, with 20G gravity if
+    /// `instant_gravity` is set.
+    pub fn best(&self, board: &Board, block: &Block, instant_gravity: bool) -> Option<Placement> {
+        self.placements(board, block, instant_gravity)
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    /// Every placement `block` can reach on `board`, scored.
+    pub fn placements(
+        &self,
+        board: &Board,
+        block: &Block,
+        instant_gravity: bool,
+    ) -> Vec<Placement> {
+        routes(board, block, instant_gravity)
+            .into_iter()
+            .map(|route| Placement {
+                score: self.evaluate(board, &route.block),
+                block: route.block,
+                inputs: route.inputs,
+            })
+            .collect()
+    }
+
+    /// Scores `board` after `block` locks on it and full rows clear.
+    pub fn evaluate(&self, board: &Board, block: &Block) -> f64 {
+        let mut board = board.clone();
+        board.place_block(block);
+        let lines = board.clear_board();
+        let heights = column_heights(&board);
+        let w = &self.weights;
+        w.height * heights.iter().sum::<i32>() as f64
+            + w.lines * lines as f64
+            + w.holes * holes(&board, &heights) as f64
+            + w.bumpiness * bumpiness(&heights) as f64
+    }
+}
+
+impl Player for Bot {
+    fn plan(&mut self, game: &Game) -> Result<Vec<Action>, Box<dyn Error>> {
+        Ok(self
+            .choose(game)
+            .map(|placement| placement.inputs)
+            .unwrap_or_default())
+    }
+}
+
+/// Has `player` place the falling block. Returns whether there was a block
+/// to place.
+pub fn place(player: &mut dyn Player, game: &mut Game) -> Result<bool, Box<dyn Error>> {
+    if game.ended() || game.active_block().is_none() {
+        return Ok(false);
+    }
+    let inputs = player.plan(game)?;
+    if inputs.is_empty() {
+        return Err("the bot has no move".into());
+    }
+    for action in inputs {
+        game.apply(action);
+    }
+    Ok(true)
+}
+
+/// Plays `game` without a terminal until it ends or `max_pieces` have been
+/// placed, letting frames pass only while waiting for a block.
+pub fn play(
+    player: &mut dyn Player,
+    game: &mut Game,
+    max_pieces: u32,
+) -> Result<(), Box<dyn Error>> {
+    while !game.ended() && game.pieces < max_pieces {
+        if !place(player, game)? {
+            game.tick();
+        }
+    }
+    Ok(())
+}
+
+/// The height of each column's highest filled cell above the floor.
+pub(crate) fn column_heights(board: &Board) -> Vec<i32> {
+    let mut heights = vec![0; board.x_dim as usize];
+    for point in board.cells.keys() {
+        let column = &mut heights[point.get_x() as usize];
+        *column = (*column).max(board.y_dim - point.get_y());
+    }
+    heights
+}
+
+/// Empty cells below the top of their column.
+pub(crate) fn holes(board: &Board, heights: &[i32]) -> i32 {
+    heights
+        .iter()
+        .enumerate()
+        .map(|(x, &height)| {
+            (board.y_dim - height..board.y_dim)
+                .filter(|&y| board.cell(&Point::new(x as i32, y)).is_none())
+                .count() as i32
+        })
+        .sum()
+}
+
+/// The summed height differences between neighbouring columns.
+pub(crate) fn bumpiness(heights: &[i32]) -> i32 {
+    heights.windows(2).map(|w| (w[0] - w[1]).abs()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockShape;
+    use crate::board::Cell;
+    use crate::game::{GameConfig, GameMode};
+
+    #[test]
+    fn test_weights_round_trip() {
+        let weights = Weights {
+            height: -0.25,
+            lines: 1.5,
+            holes: -0.75,
+            bumpiness: -0.125,
+        };
+        assert_eq!(weights.to_string().parse(), Ok(weights));
+        let partial: Weights = "# tuned\nholes = -2\n".parse().unwrap();
+        assert_eq!(partial.holes, -2.0);
+        assert_eq!(partial.height, Weights::default().height);
+        assert_eq!(
+            "holes = -2\nwells = 1\n".parse::<Weights>(),
+            Err("line 2: unknown weight 'wells'".to_string())
+        );
+        assert!("lines = many".parse::<Weights>().is_err());
+    }
+
+    #[test]
+    fn test_tucks_under_overhangs() {
+        let mut game = Game::new(GameConfig::default());
+        game.board = Board::from_rows(10, 20, &["....######", "..........", ".........."]).unwrap();
+        game.spawn(BlockShape::Square);
+
+        // Sliding the O under the overhang fills part of the gap there
+        let placement = Bot::default().choose(&game).unwrap();
+        assert!(placement.inputs.contains(&Action::SoftDrop));
+        place(&mut Bot::default(), &mut game).unwrap();
+        assert!(game.board.rows()[19][4..].contains("OO"));
+    }
+
+    #[test]
+    fn test_plays_at_twenty_g() {
+        // A well between a shelf and the platform the O lands on
+        let setup = || {
+            let mut game = Game::new(GameConfig {
+                mode: GameMode::Master,
+                seed: 5,
+                ..GameConfig::default()
+            });
+            game.level = 500;
+            game.board = Board::from_rows(10, 20, &["##..#####."; 10]).unwrap();
+            game.spawn(BlockShape::Square);
+            game
+        };
+        let game = setup();
+        assert!(game.gravity().is_instant());
+
+        // The O falls into the well as it moves over it, so the shelf is out
+        // of reach and every placement ends up where it says
+        let block = game.active_block().unwrap();
+        for placement in Bot::default().placements(&game.board, block, true) {
+            assert!(placement.block.coordinates.iter().all(|p| p.get_x() >= 2));
+            let mut game = setup();
+            for action in &placement.inputs {
+                game.apply(*action);
+            }
+            for point in &placement.block.coordinates {
+                assert_eq!(
+                    game.board.cell(point),
+                    Some(Cell::Block(BlockShape::Square))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_features() {
+        let board: Board = "
+            ..........
+            ##........
+            #.#.......
+            ###.....##
+        "
+        .parse()
+        .unwrap();
+        let heights = column_heights(&board);
+        assert_eq!(heights, [3, 3, 2, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(holes(&board, &heights), 1);
+        assert_eq!(bumpiness(&heights), 4);
+    }
+
+    #[test]
+    fn test_takes_the_line_clear() {
+        let mut game = Game::new(GameConfig::default());
+        game.board = Board::from_rows(10, 20, &["####.#####"]).unwrap();
+        game.spawn(BlockShape::Line);
+
+        let placement = Bot::default().choose(&game).unwrap();
+        assert!(placement.block.coordinates.contains(&Point::new(4, 19)));
+        place(&mut Bot::default(), &mut game).unwrap();
+        assert_eq!(game.lines, 1);
+    }
+
+    #[test]
+    fn test_plays_a_long_game() {
+        let mut game = Game::new(GameConfig {
+            seed: 11,
+            ..GameConfig::default()
+        });
+        play(&mut Bot::default(), &mut game, 300).unwrap();
+        assert!(!game.ended());
+        assert_eq!(game.pieces, 300);
+        assert!(game.lines >= 100);
+    }
+}
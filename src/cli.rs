@@ -0,0 +1,340 @@
+use crate::config::Settings;
+use crate::theme::Theme;
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::path::PathBuf;
+use tetris::game::{GameMode, Randomizer};
+use tetris::simulate::Format;
+
+/// A terminal Tetris game.
+///
+/// Settings are taken from the defaults, then the config file (if any), then
+/// the options given on the command line.
+#[derive(Debug, Parser)]
+#[command(name = "tetris", version)]
+pub struct Cli {
+    /// Config file with `key = value` lines (keys as the long options below)
+    #[arg(short, long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Game mode [possible values: endless, sprint, ultra, dig, marathon, master, practice]
+    #[arg(short, long, global = true)]
+    pub mode: Option<GameMode>,
+
+    /// Seed for the piece sequence; random if not given
+    #[arg(short, long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Starting level (1-15), controls the drop speed
+    #[arg(short, long, global = true)]
+    pub level: Option<u32>,
+
+    /// Board width in cells
+    #[arg(long, global = true)]
+    pub width: Option<usize>,
+
+    /// Board height in cells
+    #[arg(long, global = true)]
+    pub height: Option<usize>,
+
+    /// Length of an ultra game in seconds
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub time_limit: Option<u32>,
+
+    /// Garbage rows to clear in dig mode
+    #[arg(long, value_name = "ROWS", global = true)]
+    pub garbage_lines: Option<u32>,
+
+    /// Garbage rows on the board at once in dig mode
+    #[arg(long, value_name = "ROWS", global = true)]
+    pub garbage_height: Option<usize>,
+
+    /// Percent chance that each garbage hole moves from the one below it
+    #[arg(long, value_name = "PERCENT", global = true)]
+    pub messiness: Option<u32>,
+
+    /// Lines to clear in marathon mode
+    #[arg(long, value_name = "LINES", global = true)]
+    pub marathon_lines: Option<u32>,
+
+    /// Keep playing a marathon after its goal is reached
+    #[arg(long, global = true)]
+    pub endless_after_goal: bool,
+
+    /// How pieces are dealt [possible values: random, bag]
+    #[arg(long, global = true)]
+    pub randomizer: Option<Randomizer>,
+
+    /// Cell style [possible values: classic, blocks, color]
+    #[arg(short, long)]
+    pub theme: Option<Theme>,
+
+    /// Record the game to a replay file when it ends
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Watch a recorded replay instead of playing; game settings come from the file
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Save the game state to this file when quitting mid-game
+    #[arg(long, value_name = "FILE")]
+    pub save: Option<PathBuf>,
+
+    /// Resume a game saved with --save; game settings come from the file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub resume: Option<PathBuf>,
+
+    /// Play a puzzle: a starting board, a piece sequence and a goal
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["replay", "resume"])]
+    pub puzzle: Option<PathBuf>,
+
+    /// Start from a fumen setup (v115 data or a fumen URL): its board, piece,
+    /// hold and next pieces
+    #[arg(long, value_name = "FUMEN", conflicts_with_all = ["replay", "resume", "puzzle", "record"])]
+    pub fumen: Option<String>,
+
+    /// Page of the --fumen setup to start from
+    #[arg(long, value_name = "PAGE", default_value_t = 1, requires = "fumen")]
+    pub fumen_page: usize,
+
+    /// Train an opener from a file: a practice game dealing bags of pieces,
+    /// with the next placement suggested and misplaced cells marked
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["replay", "resume", "puzzle", "fumen", "record"])]
+    pub opener: Option<PathBuf>,
+
+    /// Let the built-in bot play while you watch
+    #[arg(long, group = "player", conflicts_with = "replay")]
+    pub bot: bool,
+
+    /// Let an external bot play over the Tetris Bot Protocol: the command
+    /// that starts it, with its arguments
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        group = "player",
+        conflicts_with = "replay"
+    )]
+    #[cfg(feature = "tbp")]
+    pub tbp_bot: Option<String>,
+
+    /// Serve the built-in bot over the Tetris Bot Protocol on stdin and
+    /// stdout instead of playing
+    #[arg(long, conflicts_with_all = ["replay", "resume", "puzzle", "fumen", "opener", "player", "record", "save"])]
+    #[cfg(feature = "tbp")]
+    pub tbp: bool,
+
+    /// Weights file for the built-in bot's evaluation, as written by `tune`
+    #[arg(long, value_name = "FILE", global = true)]
+    pub weights: Option<PathBuf>,
+
+    /// Run the bot without a terminal and print how the game went
+    #[arg(long, requires = "player")]
+    pub headless: bool,
+
+    /// Most pieces the bot places in a headless game
+    #[arg(
+        long,
+        value_name = "PIECES",
+        default_value_t = 10000,
+        requires = "headless"
+    )]
+    pub max_pieces: u32,
+
+    /// Print the final position as a fumen when leaving the game
+    #[arg(long, conflicts_with = "replay")]
+    pub export_fumen: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Play many bot games without a terminal, spread over threads, and
+    /// print statistics about them. Games use the game options above, with
+    /// consecutive seeds from --seed.
+    Simulate(SimulateArgs),
+    /// Evolve the built-in bot's weights with a genetic algorithm, starting
+    /// from --weights or the defaults, and write the best set to a file.
+    /// Every candidate plays the same games, from --seed.
+    Tune(TuneArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Number of games to play
+    #[arg(long, default_value_t = 100)]
+    pub games: u32,
+
+    /// Most pieces placed in each game
+    #[arg(long, value_name = "PIECES", default_value_t = 1000)]
+    pub max_pieces: u32,
+
+    /// Threads to play on; all available cores if not given
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// Play an external bot over the Tetris Bot Protocol instead of the
+    /// built-in one, one process per thread
+    #[arg(long, value_name = "COMMAND")]
+    #[cfg(feature = "tbp")]
+    pub tbp_bot: Option<String>,
+
+    /// Output format [possible values: csv, json]
+    #[arg(long, default_value_t = Format::Csv)]
+    pub format: Format,
+
+    /// Print one line per game instead of the summary
+    #[arg(long)]
+    pub per_game: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TuneArgs {
+    /// File to write the best weights to
+    #[arg(long, value_name = "FILE")]
+    pub output: PathBuf,
+
+    /// Weight sets in each generation
+    #[arg(long, default_value_t = 20)]
+    pub population: usize,
+
+    /// Generations to evolve
+    #[arg(long, default_value_t = 10)]
+    pub generations: u32,
+
+    /// Games each weight set plays; its fitness is the lines cleared in them
+    #[arg(long, default_value_t = 5)]
+    pub games: u32,
+
+    /// Most pieces placed in each game
+    #[arg(long, value_name = "PIECES", default_value_t = 500)]
+    pub max_pieces: u32,
+
+    /// Chance that a new weight set has a weight nudged, from 0 to 1
+    #[arg(long, value_name = "CHANCE", default_value_t = 0.2)]
+    pub mutation: f64,
+
+    /// Threads to play on; all available cores if not given
+    #[arg(long)]
+    pub threads: Option<usize>,
+}
+
+impl Cli {
+    /// Resolves the final settings from the defaults, the config file and the
+    /// command-line options, in that order of precedence.
+    pub fn settings(&self) -> Result<Settings, Box<dyn Error>> {
+        let mut settings = match &self.config {
+            Some(path) => Settings::from_file(path)?,
+            None => Settings::default(),
+        };
+
+        if let Some(mode) = self.mode {
+            settings.game.mode = mode;
+        }
+        if let Some(seed) = self.seed {
+            settings.game.seed = seed;
+        }
+        if let Some(level) = self.level {
+            settings.game.level = level;
+        }
+        if let Some(width) = self.width {
+            settings.game.width = width;
+        }
+        if let Some(height) = self.height {
+            settings.game.height = height;
+        }
+        if let Some(time_limit) = self.time_limit {
+            settings.game.time_limit = time_limit;
+        }
+        if let Some(garbage_lines) = self.garbage_lines {
+            settings.game.garbage_lines = garbage_lines;
+        }
+        if let Some(garbage_height) = self.garbage_height {
+            settings.game.garbage_height = garbage_height;
+        }
+        if let Some(messiness) = self.messiness {
+            settings.game.messiness = messiness;
+        }
+        if let Some(marathon_lines) = self.marathon_lines {
+            settings.game.marathon_lines = marathon_lines;
+        }
+        if self.endless_after_goal {
+            settings.game.marathon_endless = true;
+        }
+        if let Some(randomizer) = self.randomizer {
+            settings.game.randomizer = randomizer;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+        }
+
+        settings.game.validate()?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_overrides_defaults() {
+        let cli = Cli::parse_from([
+            "tetris", "--seed", "9", "--level", "4", "--width", "8", "--theme", "blocks",
+        ]);
+        let settings = cli.settings().unwrap();
+
+        assert_eq!(settings.game.seed, 9);
+        assert_eq!(settings.game.level, 4);
+        assert_eq!(settings.game.width, 8);
+        assert_eq!(settings.game.height, 20);
+        assert_eq!(settings.theme, Theme::Blocks);
+    }
+
+    #[test]
+    fn test_cli_rejects_invalid_settings() {
+        let cli = Cli::parse_from(["tetris", "--level", "99"]);
+        assert!(cli.settings().is_err());
+
+        assert!(Cli::try_parse_from(["tetris", "--mode", "nonsense"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "--record", "a", "--replay", "b"]).is_err());
+        assert!(
+            Cli::try_parse_from(["tetris", "--fumen", "v115@vhAAgH", "--record", "a"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["tetris", "--fumen-page", "2"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "--opener", "a", "--puzzle", "b"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "--randomizer", "shuffle"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "--headless"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "--bot", "--max-pieces", "5"]).is_err());
+        assert!(Cli::try_parse_from(["tetris", "tune"]).is_err());
+        #[cfg(feature = "tbp")]
+        {
+            assert!(Cli::try_parse_from(["tetris", "--bot", "--tbp-bot", "cold-clear"]).is_err());
+            assert!(Cli::try_parse_from(["tetris", "--tbp", "--bot"]).is_err());
+            assert!(
+                Cli::try_parse_from(["tetris", "--tbp-bot", "cold-clear", "--headless"]).is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulate_takes_game_options() {
+        let cli = Cli::parse_from([
+            "tetris", "--seed", "5", "simulate", "--games", "8", "--mode", "sprint", "--format",
+            "json",
+        ]);
+        let settings = cli.settings().unwrap();
+        assert_eq!(settings.game.seed, 5);
+        assert_eq!(settings.game.mode, GameMode::Sprint);
+        let Some(Command::Simulate(args)) = cli.command else {
+            panic!("expected the simulate command");
+        };
+        assert_eq!(args.games, 8);
+        assert_eq!(args.max_pieces, 1000);
+        assert_eq!(args.format, Format::Json);
+
+        assert!(Cli::try_parse_from(["tetris", "simulate", "--format", "xml"]).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::theme::Theme;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use tetris::game::GameConfig;
+
+/// All user-facing settings: the engine configuration plus presentation options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub game: GameConfig,
+    pub theme: Theme,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            game: GameConfig::default(),
+            theme: Theme::Classic,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Settings {
+    /// Reads a config file on top of the defaults.
+    pub fn from_file(path: &Path) -> Result<Settings, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config {}: {}", path.display(), e))?;
+        let mut settings = Settings::default();
+        settings.apply_config(&text)?;
+        Ok(settings)
+    }
+
+    /// Applies `key = value` lines. Blank lines and lines starting with `#`
+    /// are ignored; keys are the same as the long command-line options.
+    pub fn apply_config(&mut self, text: &str) -> Result<(), ConfigError> {
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| ConfigError {
+                line: index + 1,
+                message,
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected 'key = value', got '{}'", line)))?;
+            let value = value.trim().trim_matches('"');
+            self.set(key.trim(), value).map_err(error)?;
+        }
+        Ok(())
+    }
+
+    /// Sets a single setting by name.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "theme" => self.theme = value.parse()?,
+            _ => self.game.set(key, value)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tetris::game::GameMode;
+
+    #[test]
+    fn test_apply_config() {
+        let mut settings = Settings::default();
+        settings
+            .apply_config(
+                "# my settings\n\
+                 mode = endless\n\
+                 seed = 42\n\
+                 \n\
+                 width = 12\n\
+                 theme = \"color\"\n",
+            )
+            .unwrap();
+
+        assert_eq!(settings.game.mode, GameMode::Endless);
+        assert_eq!(settings.game.seed, 42);
+        assert_eq!(settings.game.width, 12);
+        assert_eq!(settings.game.height, 20);
+        assert_eq!(settings.theme, Theme::Color);
+    }
+
+    #[test]
+    fn test_apply_config_takes_option_names() {
+        let mut settings = Settings::default();
+        settings
+            .apply_config(
+                "time-limit = 60
+                 garbage-lines = 5
+                 endless-after-goal = true
+",
+            )
+            .unwrap();
+
+        assert_eq!(settings.game.time_limit, 60);
+        assert_eq!(settings.game.garbage_lines, 5);
+        assert!(settings.game.marathon_endless);
+    }
+
+    #[test]
+    fn test_apply_config_reports_line() {
+        let mut settings = Settings::default();
+        let err = settings
+            .apply_config("seed = 1\nlevel = fast\n")
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "line 2: invalid value 'fast' for level");
+    }
+}
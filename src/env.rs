@@ -0,0 +1,500 @@
+//! A reinforcement learning environment over the engine, in the style of
+//! OpenAI Gym: [`Env::reset`] starts an episode and [`Env::step`] takes an
+//! agent's action and returns what it sees next, its reward, whether the
+//! episode is done and some details of the step.
+
+use crate::block::{Block, BlockShape};
+use crate::bot::{bumpiness, column_heights, holes};
+use crate::game::{spawn_point, Action, Game, GameConfig, PREVIEW_LENGTH};
+use crate::point::Point;
+use crate::search::{route_to, routes};
+use std::fmt;
+
+/// What an agent decides at each step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionSpace {
+    /// An input, or none, each frame; the game runs one frame per step.
+    Frame,
+    /// Where the piece goes: one of the observation's `targets`. The piece is
+    /// steered there and locked, and the game runs until the next one appears.
+    Placement,
+}
+
+/// An agent's action, which must belong to the environment's action space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvAction {
+    /// For [`ActionSpace::Frame`]. Undo and redo aren't allowed.
+    Frame(Option<Action>),
+    /// For [`ActionSpace::Placement`]: an index into `targets`.
+    Place(usize),
+}
+
+/// How rewards are made up. Each step's reward is the sum of every weight
+/// times how much its quantity changed during the step, so penalties take
+/// negative weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    /// Per line cleared.
+    pub lines: f64,
+    /// Per point scored.
+    pub score: f64,
+    /// Per piece locked.
+    pub pieces: f64,
+    /// Per hole added to the board (see [`crate::bot::Weights`]).
+    pub holes: f64,
+    /// Per cell added to the summed column heights.
+    pub height: f64,
+    /// Per cell added to the bumpiness.
+    pub bumpiness: f64,
+    /// Once, when the game is lost by topping out.
+    pub game_over: f64,
+}
+
+impl Default for Rewards {
+    /// A point per line and a point off for losing.
+    fn default() -> Self {
+        Rewards {
+            lines: 1.0,
+            score: 0.0,
+            pieces: 0.0,
+            holes: 0.0,
+            height: 0.0,
+            bumpiness: 0.0,
+            game_over: -1.0,
+        }
+    }
+}
+
+impl Rewards {
+    /// Sets a single weight by name.
+    pub fn set(&mut self, key: &str, value: f64) -> Result<(), String> {
+        let weight = match key {
+            "lines" => &mut self.lines,
+            "score" => &mut self.score,
+            "pieces" => &mut self.pieces,
+            "holes" => &mut self.holes,
+            "height" => &mut self.height,
+            "bumpiness" => &mut self.bumpiness,
+            "game_over" => &mut self.game_over,
+            _ => return Err(format!("unknown reward '{}'", key)),
+        };
+        *weight = value;
+        Ok(())
+    }
+}
+
+/// A place the agent can put the current piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub block: Block,
+    /// Whether hold is used first, placing the held (or next) piece instead.
+    pub hold: bool,
+}
+
+/// What the agent sees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// The locked cells, top row first.
+    pub board: Vec<Vec<bool>>,
+    /// The falling piece, or `None` between pieces and after the game.
+    pub piece: Option<Block>,
+    pub hold: Option<BlockShape>,
+    pub can_hold: bool,
+    /// The upcoming pieces shown to a player.
+    pub queue: Vec<BlockShape>,
+    /// Every placement of the piece, and of the one hold gives, for
+    /// [`ActionSpace::Placement`]; empty for [`ActionSpace::Frame`].
+    pub targets: Vec<Target>,
+}
+
+/// Details of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    /// Lines cleared during the step.
+    pub lines: i32,
+    /// Totals so far.
+    pub score: i32,
+    pub pieces: u32,
+    pub frame: u64,
+    /// Whether the game ended by topping out rather than by reaching its goal.
+    pub topped_out: bool,
+}
+
+#[derive(Debug)]
+pub struct EnvError {
+    message: String,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+fn invalid(message: String) -> EnvError {
+    EnvError { message }
+}
+
+/// The quantities rewards are made of, at one moment.
+#[derive(Debug, Clone, Copy)]
+struct Tally {
+    lines: i32,
+    score: i32,
+    pieces: u32,
+    holes: i32,
+    height: i32,
+    bumpiness: i32,
+    game_over: bool,
+}
+
+impl Tally {
+    fn of(game: &Game) -> Tally {
+        let heights = column_heights(&game.board);
+        Tally {
+            lines: game.lines,
+            score: game.score,
+            pieces: game.pieces,
+            holes: holes(&game.board, &heights),
+            height: heights.iter().sum(),
+            bumpiness: bumpiness(&heights),
+            game_over: game.game_over,
+        }
+    }
+}
+
+/// An environment playing games with `config`'s rules, apart from the seed
+/// which each episode chooses.
+pub struct Env {
+    config: GameConfig,
+    actions: ActionSpace,
+    rewards: Rewards,
+    game: Game,
+    targets: Vec<Target>,
+}
+
+impl Env {
+    /// Fails if `config` isn't a playable game, so that every episode
+    /// `reset` starts is one.
+    pub fn new(
+        config: GameConfig,
+        actions: ActionSpace,
+        rewards: Rewards,
+    ) -> Result<Env, EnvError> {
+        config.validate().map_err(invalid)?;
+        // Agents can't undo and nobody watches their finesse, so neither is
+        // worth keeping track of
+        let config = GameConfig {
+            undo: false,
+            finesse: false,
+            ..config
+        };
+        let game = Game::new(config.clone());
+        let mut env = Env {
+            config,
+            actions,
+            rewards,
+            game,
+            targets: Vec::new(),
+        };
+        env.targets = env.targets();
+        Ok(env)
+    }
+
+    /// The game being played, for anything the observation leaves out.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Starts a new episode with the piece sequence of `seed`.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(GameConfig {
+            seed,
+            ..self.config.clone()
+        });
+        self.observe()
+    }
+
+    /// Takes `action` and returns the observation after it, the reward for
+    /// it, whether the episode is over and details of the step.
+    pub fn step(&mut self, action: EnvAction) -> Result<(Observation, f64, bool, Info), EnvError> {
+        if self.game.ended() {
+            return Err(invalid(
+                "the episode is over; reset to start another".to_string(),
+            ));
+        }
+        let before = Tally::of(&self.game);
+        match (self.actions, action) {
+            (ActionSpace::Frame, EnvAction::Frame(input)) => {
+                match input {
+                    Some(Action::Undo | Action::Redo) => {
+                        return Err(invalid("undo and redo aren't agent actions".to_string()))
+                    }
+                    Some(input) => {
+                        self.game.apply(input);
+                    }
+                    None => {}
+                }
+                self.game.tick();
+            }
+            (ActionSpace::Placement, EnvAction::Place(index)) => self.place(index)?,
+            (actions, action) => {
+                return Err(invalid(format!(
+                    "{:?} doesn't belong to the {:?} action space",
+                    action, actions
+                )))
+            }
+        }
+        let after = Tally::of(&self.game);
+
+        let r = &self.rewards;
+        let mut reward = r.lines * (after.lines - before.lines) as f64
+            + r.score * (after.score - before.score) as f64
+            + r.pieces * (after.pieces - before.pieces) as f64
+            + r.holes * (after.holes - before.holes) as f64
+            + r.height * (after.height - before.height) as f64
+            + r.bumpiness * (after.bumpiness - before.bumpiness) as f64;
+        if after.game_over && !before.game_over {
+            reward += r.game_over;
+        }
+        let info = Info {
+            lines: after.lines - before.lines,
+            score: self.game.score,
+            pieces: self.game.pieces,
+            frame: self.game.frame,
+            topped_out: self.game.game_over,
+        };
+        Ok((self.observe(), reward, self.game.ended(), info))
+    }
+
+    /// Steers the piece to the target at `index` and locks it, then lets the
+    /// game run until the next piece appears.
+    fn place(&mut self, index: usize) -> Result<(), EnvError> {
+        let target = self.targets.get(index).cloned().ok_or_else(|| {
+            invalid(format!(
+                "there are {} targets, not {}",
+                self.targets.len(),
+                index + 1
+            ))
+        })?;
+        if target.hold {
+            self.game.apply(Action::Hold);
+        }
+        let inputs = self
+            .game
+            .active_block()
+            .and_then(|block| {
+                route_to(
+                    &self.game.board,
+                    block,
+                    &target.block,
+                    self.game.gravity().is_instant(),
+                )
+            })
+            .ok_or_else(|| invalid("the target can't be reached".to_string()))?;
+        for input in inputs {
+            self.game.apply(input);
+        }
+        while !self.game.ended() && self.game.active_block().is_none() {
+            self.game.tick();
+        }
+        Ok(())
+    }
+
+    fn observe(&mut self) -> Observation {
+        self.targets = self.targets();
+        let board = &self.game.board;
+        Observation {
+            board: (0..board.y_dim)
+                .map(|y| {
+                    (0..board.x_dim)
+                        .map(|x| board.cell(&Point::new(x, y)).is_some())
+                        .collect()
+                })
+                .collect(),
+            piece: self
+                .game
+                .active_block()
+                .filter(|_| !self.game.ended())
+                .cloned(),
+            hold: self.game.hold,
+            can_hold: self.game.can_hold,
+            queue: self
+                .game
+                .queue
+                .iter()
+                .take(PREVIEW_LENGTH)
+                .copied()
+                .collect(),
+            targets: self.targets.clone(),
+        }
+    }
+
+    /// Every placement of the current piece, then of the piece hold gives.
+    fn targets(&self) -> Vec<Target> {
+        let game = &self.game;
+        let block = match game.active_block() {
+            Some(block) if self.actions == ActionSpace::Placement && !game.ended() => block,
+            _ => return Vec::new(),
+        };
+        let instant = game.gravity().is_instant();
+        let mut targets: Vec<Target> = routes(&game.board, block, instant)
+            .into_iter()
+            .map(|route| Target {
+                block: route.block,
+                hold: false,
+            })
+            .collect();
+        let held = game.hold.or_else(|| game.queue.front().copied());
+        if let Some(shape) = held.filter(|shape| game.can_hold && *shape != block.shape) {
+            let start = Block::new(spawn_point(&game.board), shape);
+            if !game.board.block_touches(&start) {
+                targets.extend(
+                    routes(&game.board, &start, instant)
+                        .into_iter()
+                        .map(|route| Target {
+                            block: route.block,
+                            hold: true,
+                        }),
+                );
+            }
+        }
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    fn config() -> GameConfig {
+        GameConfig {
+            seed: 1,
+            ..GameConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_new_rejects_bad_config() {
+        let config = GameConfig {
+            width: 0,
+            ..config()
+        };
+        let error = Env::new(config, ActionSpace::Placement, Rewards::default()).err();
+        assert_eq!(
+            error.unwrap().to_string(),
+            "board must be at least 4x4, got 0x20"
+        );
+    }
+
+    #[test]
+    fn test_reset_is_seeded() {
+        let mut env = Env::new(config(), ActionSpace::Placement, Rewards::default()).unwrap();
+        let first = env.reset(7);
+        env.step(EnvAction::Place(0)).unwrap();
+        assert_eq!(env.reset(7), first);
+        assert_ne!(env.reset(8).queue, first.queue);
+
+        assert_eq!(first.board.len(), 20);
+        assert!(first
+            .board
+            .iter()
+            .all(|row| row.len() == 10 && !row.contains(&true)));
+        assert_eq!(first.queue.len(), PREVIEW_LENGTH);
+        assert!(first.piece.is_some());
+        // Holding swaps in the next piece, unless it's the same shape
+        let holds = first.targets.iter().filter(|t| t.hold).count();
+        let same = first.piece.as_ref().map(|block| block.shape) == Some(first.queue[0]);
+        assert_eq!(holds == 0, same);
+        assert!(holds < first.targets.len());
+    }
+
+    #[test]
+    fn test_placements_are_rewarded() {
+        let rewards = Rewards {
+            holes: -0.5,
+            ..Rewards::default()
+        };
+        let mut env = Env::new(config(), ActionSpace::Placement, rewards).unwrap();
+        env.game.board = Board::from_rows(10, 20, &["####.#####"]).unwrap();
+        env.game.hold = Some(BlockShape::Line);
+        env.game.spawn(BlockShape::Square);
+        let observation = env.observe();
+
+        // Holding for the I and dropping it in the gap clears the line
+        let index = observation
+            .targets
+            .iter()
+            .position(|t| t.hold && t.block.coordinates.contains(&Point::new(4, 19)))
+            .unwrap();
+        let (observation, reward, done, info) = env.step(EnvAction::Place(index)).unwrap();
+        assert_eq!(reward, 1.0);
+        assert!(!done);
+        assert_eq!(info.lines, 1);
+        assert_eq!(info.pieces, 1);
+        assert_eq!(observation.hold, Some(BlockShape::Square));
+        assert!(observation.can_hold);
+        assert_eq!(observation.board[19].iter().filter(|&&c| c).count(), 1);
+
+        // An O on the rest of the I covers three holes beside it
+        env.game.spawn(BlockShape::Square);
+        let observation = env.observe();
+        let index = observation
+            .targets
+            .iter()
+            .position(|t| t.block.coordinates.contains(&Point::new(5, 16)))
+            .unwrap();
+        let (_, reward, _, _) = env.step(EnvAction::Place(index)).unwrap();
+        assert_eq!(reward, -1.5);
+    }
+
+    #[test]
+    fn test_rewards_by_name() {
+        let mut rewards = Rewards::default();
+        rewards.set("holes", -0.25).unwrap();
+        assert_eq!(rewards.holes, -0.25);
+        assert_eq!(
+            rewards.set("tetrises", 4.0),
+            Err("unknown reward 'tetrises'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut env = Env::new(config(), ActionSpace::Frame, Rewards::default()).unwrap();
+        let observation = env.reset(3);
+        assert!(observation.targets.is_empty());
+
+        let (_, reward, _, info) = env.step(EnvAction::Frame(None)).unwrap();
+        assert_eq!((reward, info.frame, info.pieces), (0.0, 1, 0));
+        let (_, _, _, info) = env.step(EnvAction::Frame(Some(Action::HardDrop))).unwrap();
+        assert_eq!((info.frame, info.pieces), (2, 1));
+
+        assert!(env.step(EnvAction::Place(0)).is_err());
+        assert!(env.step(EnvAction::Frame(Some(Action::Undo))).is_err());
+    }
+
+    #[test]
+    fn test_episodes_end() {
+        let rewards = Rewards {
+            game_over: -10.0,
+            ..Rewards::default()
+        };
+        let mut env = Env::new(config(), ActionSpace::Frame, rewards).unwrap();
+        env.reset(5);
+        let mut total = 0.0;
+        loop {
+            let (_, reward, done, info) =
+                env.step(EnvAction::Frame(Some(Action::HardDrop))).unwrap();
+            total += reward;
+            if done {
+                assert!(info.topped_out);
+                break;
+            }
+        }
+        assert_eq!(total, -10.0);
+        assert!(env.step(EnvAction::Frame(None)).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::game::Action;
+
+/// How many steering presses (left, right and rotate) a placement took,
+/// against the fewest that reach the same spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finesse {
+    pub presses: u32,
+    pub minimum: u32,
+}
+
+impl Finesse {
+    /// Presses beyond the minimum.
+    pub fn extra(&self) -> u32 {
+        self.presses.saturating_sub(self.minimum)
+    }
+
+    pub fn is_fault(&self) -> bool {
+        self.extra() > 0
+    }
+}
+
+/// Whether an action steers the block. Only these count towards finesse;
+/// drops don't, since gravity moves the block down anyway.
+pub fn is_steering(action: Action) -> bool {
+    matches!(action, Action::Left | Action::Right | Action::Rotate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameConfig};
+
+    #[test]
+    fn test_game_counts_faults() {
+        let mut game = Game::new(GameConfig {
+            seed: 3,
+            finesse: true,
+            ..GameConfig::default()
+        });
+        game.apply(Action::HardDrop);
+        assert_eq!(game.finesse.unwrap().extra(), 0);
+
+        game.apply(Action::Left);
+        game.apply(Action::Right);
+        game.apply(Action::Right);
+        game.apply(Action::HardDrop);
+        let finesse = game.finesse.unwrap();
+        assert_eq!((finesse.presses, finesse.minimum), (3, 1));
+        assert_eq!(game.finesse_faults, 1);
+    }
+
+    #[test]
+    fn test_finesse_is_opt_in() {
+        let mut game = Game::new(GameConfig {
+            seed: 3,
+            ..GameConfig::default()
+        });
+        game.apply(Action::Left);
+        game.apply(Action::Right);
+        game.apply(Action::HardDrop);
+        assert!(game.finesse.is_none());
+        assert_eq!(game.finesse_faults, 0);
+    }
+}
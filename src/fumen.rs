@@ -0,0 +1,909 @@
+use crate::block::{Block, BlockShape};
+use crate::board::{Board, Cell};
+use crate::game::Game;
+use crate::point::Point;
+use std::fmt;
+
+/// Fumen fields are 10 cells wide with 23 visible rows, plus a garbage row
+/// below the floor that the format uses for rising garbage.
+pub const FUMEN_WIDTH: i32 = 10;
+pub const FUMEN_HEIGHT: i32 = 23;
+const FIELD_CELLS: usize = ((FUMEN_HEIGHT + 1) * FUMEN_WIDTH) as usize;
+
+const ENCODE_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const COMMENT_TABLE: &str =
+    " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = 96;
+
+/// Field cell values: 0 is empty, then I, L, O, Z, T, J, S and 8 for garbage.
+const GARBAGE: u8 = 8;
+
+/// One page of a fumen: the field, the piece placed on it and a comment.
+///
+/// Boards are 10x23 with row 0 at the top, like game boards. Each page's
+/// field is what it looks like before its piece is placed; the next page
+/// starts from that field with the piece locked and full lines cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub board: Board,
+    /// The piece in the position where it is placed.
+    pub piece: Option<Block>,
+    pub comment: String,
+}
+
+impl Page {
+    pub fn new(board: Board) -> Page {
+        Page {
+            board,
+            piece: None,
+            comment: String::new(),
+        }
+    }
+
+    /// The hold, current and next pieces, when the comment holds them in
+    /// fumen's quiz form (`#Q=[hold](current)next`).
+    pub fn quiz(&self) -> Option<Quiz> {
+        self.comment.parse().ok()
+    }
+}
+
+/// The pieces available on a page, as written in a quiz comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quiz {
+    pub hold: Option<BlockShape>,
+    pub current: Option<BlockShape>,
+    pub next: Vec<BlockShape>,
+}
+
+impl Quiz {
+    /// The quiz after `used` has been placed, taken from the current piece,
+    /// from hold, or from the next queue by holding the current piece.
+    fn after(&self, used: BlockShape) -> Quiz {
+        let mut next = self.next.clone();
+        let mut take = || (!next.is_empty()).then(|| next.remove(0));
+        let (hold, current) = if self.current == Some(used) {
+            (self.hold, take())
+        } else if self.hold == Some(used) {
+            (self.current, take())
+        } else {
+            // Holding the current piece brings out the first of the queue
+            take();
+            (self.current, take())
+        };
+        Quiz {
+            hold,
+            current,
+            next,
+        }
+    }
+}
+
+impl fmt::Display for Quiz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = |shape: Option<BlockShape>| shape.map(|s| s.letter().to_string());
+        let next: String = self.next.iter().map(BlockShape::letter).collect();
+        write!(
+            f,
+            "#Q=[{}]({}){}",
+            letter(self.hold).unwrap_or_default(),
+            letter(self.current).unwrap_or_default(),
+            next
+        )
+    }
+}
+
+impl std::str::FromStr for Quiz {
+    type Err = FumenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || invalid("not a quiz comment");
+        let rest = s.strip_prefix("#Q=[").ok_or_else(bad)?;
+        let (hold, rest) = rest.split_once("](").ok_or_else(bad)?;
+        let (current, next) = rest.split_once(')').ok_or_else(bad)?;
+        let shape = |text: &str| match text.chars().next() {
+            None => Ok(None),
+            Some(c) => BlockShape::from_letter(c).map(Some).ok_or_else(bad),
+        };
+        Ok(Quiz {
+            hold: shape(hold)?,
+            current: shape(current)?,
+            next: next
+                .chars()
+                .map(|c| BlockShape::from_letter(c).ok_or_else(bad))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct FumenError {
+    message: String,
+}
+
+impl fmt::Display for FumenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid fumen: {}", self.message)
+    }
+}
+
+impl std::error::Error for FumenError {}
+
+fn invalid(message: &str) -> FumenError {
+    FumenError {
+        message: message.to_string(),
+    }
+}
+
+/// Decodes every page of a v115 fumen. URLs are accepted as well as bare
+/// `v115@...` data.
+pub fn decode(fumen: &str) -> Result<Vec<Page>, FumenError> {
+    let start = fumen
+        .find("115@")
+        .filter(|&i| i > 0 && "vVmMdD".contains(&fumen[i - 1..i]))
+        .ok_or_else(|| invalid("expected v115 data"))?;
+    let mut reader = Reader::new(&fumen[start + 4..])?;
+
+    let mut pages = Vec::new();
+    let mut previous = [0u8; FIELD_CELLS];
+    let mut repeat = 0;
+    let mut comment = String::new();
+    let mut quiz: Option<Quiz> = None;
+
+    while !reader.is_empty() {
+        let mut field = previous;
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            let mut changed = true;
+            while index < FIELD_CELLS {
+                let run = reader.poll(2)? as usize;
+                let diff = run / FIELD_CELLS;
+                let count = run % FIELD_CELLS + 1;
+                if diff == 8 && count == FIELD_CELLS {
+                    changed = false;
+                }
+                if index + count > FIELD_CELLS {
+                    return Err(invalid("field overflows"));
+                }
+                for cell in &mut field[index..index + count] {
+                    *cell = (*cell as i32 + diff as i32 - 8)
+                        .try_into()
+                        .ok()
+                        .filter(|&c| c <= GARBAGE)
+                        .ok_or_else(|| invalid("bad field cell"))?;
+                }
+                index += count;
+            }
+            if !changed {
+                repeat = reader.poll(1)?;
+            }
+        }
+
+        let action = Action::decode(reader.poll(3)?)?;
+        if action.comment {
+            let length = reader.poll(2)? as usize;
+            let mut escaped = String::new();
+            for _ in 0..length.div_ceil(4) {
+                let mut value = reader.poll(5)?;
+                for _ in 0..4 {
+                    let index = (value % COMMENT_BASE) as usize;
+                    escaped.push(COMMENT_TABLE.chars().nth(index).unwrap_or(' '));
+                    value /= COMMENT_BASE;
+                }
+            }
+            escaped.truncate(length);
+            comment = unescape(&escaped);
+            quiz = comment.parse().ok();
+        } else if let Some(quiz) = &quiz {
+            comment = quiz.to_string();
+        }
+
+        let piece = match action.piece {
+            Some((shape, rotation, x, y)) => Some(piece_block(shape, rotation, x, y)?),
+            None => None,
+        };
+        pages.push(Page {
+            board: field_board(&field),
+            piece: piece.clone(),
+            comment: comment.clone(),
+        });
+
+        if action.lock {
+            if let Some(piece) = &piece {
+                fill(&mut field, piece);
+                if let Some(current) = &quiz {
+                    quiz = Some(current.after(piece.shape));
+                }
+            }
+            clear_lines(&mut field);
+            if action.rise {
+                field.copy_within(FUMEN_WIDTH as usize.., 0);
+                field[FIELD_CELLS - FUMEN_WIDTH as usize..].fill(0);
+            }
+            if action.mirror {
+                for row in
+                    field[..FIELD_CELLS - FUMEN_WIDTH as usize].chunks_mut(FUMEN_WIDTH as usize)
+                {
+                    row.reverse();
+                }
+            }
+        }
+        previous = field;
+    }
+
+    if pages.is_empty() {
+        return Err(invalid("no pages"));
+    }
+    Ok(pages)
+}
+
+/// Encodes pages as v115 fumen data, `v115@...`. Boards must be 10x23, as
+/// produced by [`decode`] and [`Game::fumen_page`].
+pub fn encode(pages: &[Page]) -> Result<String, FumenError> {
+    let mut digits = Vec::new();
+    let mut previous = [0u8; FIELD_CELLS];
+    let mut repeat_index: Option<usize> = None;
+    let mut comment = "";
+
+    for (index, page) in pages.iter().enumerate() {
+        let mut field = board_field(&page.board)?;
+
+        // Fields are stored as differences from the previous page's locked field
+        let runs = field_runs(&previous, &field);
+        let unchanged = runs == [8 * FIELD_CELLS as u32 + FIELD_CELLS as u32 - 1];
+        match repeat_index {
+            Some(i) if unchanged && digits[i] < 63 => digits[i] += 1,
+            _ => {
+                for run in runs {
+                    push(&mut digits, run, 2);
+                }
+                repeat_index = None;
+                if unchanged {
+                    digits.push(0);
+                    repeat_index = Some(digits.len() - 1);
+                }
+            }
+        }
+
+        let has_comment = page.comment != comment;
+        let action = Action {
+            piece: match &page.piece {
+                Some(piece) => Some(piece_operation(piece)?),
+                None => None,
+            },
+            rise: false,
+            mirror: false,
+            colorize: index == 0,
+            comment: has_comment,
+            lock: true,
+        };
+        push(&mut digits, action.encode(), 3);
+        if has_comment {
+            let escaped: Vec<char> = escape(&page.comment).chars().take(4095).collect();
+            push(&mut digits, escaped.len() as u32, 2);
+            for chunk in escaped.chunks(4) {
+                let value = chunk.iter().rev().fold(0, |value, c| {
+                    let index = COMMENT_TABLE.find(*c).unwrap_or(0) as u32;
+                    value * COMMENT_BASE + index
+                });
+                push(&mut digits, value, 5);
+            }
+            comment = &page.comment;
+        }
+
+        if let Some(piece) = &page.piece {
+            fill(&mut field, piece);
+        }
+        clear_lines(&mut field);
+        previous = field;
+    }
+
+    let data: String = digits
+        .iter()
+        .map(|&d| ENCODE_TABLE[d as usize] as char)
+        .collect();
+    // Long data is broken up with '?' as fumen itself does
+    let data = if data.len() < 41 {
+        data
+    } else {
+        let (head, tail) = data.split_at(42.min(data.len()));
+        let mut parts = vec![head.to_string()];
+        parts.extend(
+            tail.as_bytes()
+                .chunks(47)
+                .map(|chunk| String::from_utf8_lossy(chunk).into_owned()),
+        );
+        parts.join("?")
+    };
+    Ok(format!("v115@{}", data))
+}
+
+/// A page's piece operation: shape, rotation (0 = reverse, 1 = right,
+/// 2 = spawn, 3 = left) and the position of its rotation centre, with y
+/// counted up from the floor.
+type Operation = (BlockShape, u32, i32, i32);
+
+struct Action {
+    piece: Option<Operation>,
+    rise: bool,
+    mirror: bool,
+    colorize: bool,
+    comment: bool,
+    lock: bool,
+}
+
+impl Action {
+    fn decode(mut value: u32) -> Result<Action, FumenError> {
+        let kind = (value % 8) as u8;
+        value /= 8;
+        let rotation = value % 4;
+        value /= 4;
+        let position = (value % FIELD_CELLS as u32) as i32;
+        value /= FIELD_CELLS as u32;
+        let mut flag = || {
+            let set = value % 2 == 1;
+            value /= 2;
+            set
+        };
+        let rise = flag();
+        let mirror = flag();
+        let colorize = flag();
+        let comment = flag();
+        let lock = !flag();
+
+        let piece = match kind {
+            0 => None,
+            kind => {
+                let shape = cell_shape(kind).ok_or_else(|| invalid("bad piece"))?;
+                let (dx, dy) = position_offset(shape, rotation);
+                let x = position % FUMEN_WIDTH + dx;
+                let y = FUMEN_HEIGHT - position / FUMEN_WIDTH - 1 + dy;
+                Some((shape, rotation, x, y))
+            }
+        };
+        Ok(Action {
+            piece,
+            rise,
+            mirror,
+            colorize,
+            comment,
+            lock,
+        })
+    }
+
+    fn encode(&self) -> u32 {
+        let (kind, rotation, position) = match self.piece {
+            Some((shape, rotation, x, y)) => {
+                let (dx, dy) = position_offset(shape, rotation);
+                let (x, y) = (x - dx, y - dy);
+                let position = (FUMEN_HEIGHT - y - 1) * FUMEN_WIDTH + x;
+                (shape_cell(shape) as u32, rotation, position as u32)
+            }
+            None => (0, 0, 0),
+        };
+        let flags = [
+            self.lock,
+            self.comment,
+            self.colorize,
+            self.mirror,
+            self.rise,
+        ];
+        let mut value = 0;
+        for (i, flag) in flags.into_iter().enumerate() {
+            // The lock flag is stored inverted
+            let set = if i == 0 { !flag } else { flag };
+            value = value * 2 + set as u32;
+        }
+        ((value * FIELD_CELLS as u32 + position) * 4 + rotation) * 8 + kind
+    }
+}
+
+/// Fumen stores O, I, S and Z positions shifted from their rotation centre
+/// in some rotations; this is the shift from the stored position to the
+/// centre.
+fn position_offset(shape: BlockShape, rotation: u32) -> (i32, i32) {
+    match (shape, rotation) {
+        (BlockShape::Square, 3) => (1, -1),
+        (BlockShape::Square, 0) => (1, 0),
+        (BlockShape::Square, 2) => (0, -1),
+        (BlockShape::Line, 0) => (1, 0),
+        (BlockShape::Line, 3) => (0, -1),
+        (BlockShape::Z, 2) => (0, -1),
+        (BlockShape::Z, 1) => (-1, 0),
+        (BlockShape::ZRev, 2) => (0, -1),
+        (BlockShape::ZRev, 3) => (1, 0),
+        _ => (0, 0),
+    }
+}
+
+/// Cells of `shape` around its rotation centre in the spawn rotation, with y
+/// pointing up.
+fn spawn_cells(shape: BlockShape) -> [(i32, i32); 4] {
+    match shape {
+        BlockShape::Line => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        BlockShape::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        BlockShape::Square => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        BlockShape::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        BlockShape::LRev => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        // S
+        BlockShape::Z => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+        // Z
+        BlockShape::ZRev => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+    }
+}
+
+fn rotated_cells(shape: BlockShape, rotation: u32) -> [(i32, i32); 4] {
+    spawn_cells(shape).map(|(x, y)| match rotation {
+        0 => (-x, -y),
+        1 => (y, -x),
+        3 => (-y, x),
+        _ => (x, y),
+    })
+}
+
+/// The block a fumen operation describes, on a 10x23 board.
+fn piece_block(shape: BlockShape, rotation: u32, x: i32, y: i32) -> Result<Block, FumenError> {
+    let coordinates = rotated_cells(shape, rotation)
+        .iter()
+        .map(|(dx, dy)| Point::new(x + dx, FUMEN_HEIGHT - 1 - (y + dy)))
+        .collect();
+    let block = Block { shape, coordinates };
+    let inside = block
+        .coordinates
+        .iter()
+        .all(|p| (0..FUMEN_WIDTH).contains(&p.get_x()) && (0..FUMEN_HEIGHT).contains(&p.get_y()));
+    if inside {
+        Ok(block)
+    } else {
+        Err(invalid("piece outside the field"))
+    }
+}
+
+/// Finds the rotation and centre that place a piece on `block`'s cells.
+fn piece_operation(block: &Block) -> Result<Operation, FumenError> {
+    let mut cells: Vec<(i32, i32)> = block
+        .coordinates
+        .iter()
+        .map(|p| (p.get_x(), FUMEN_HEIGHT - 1 - p.get_y()))
+        .collect();
+    cells.sort();
+    for rotation in [2, 1, 0, 3] {
+        let offsets = rotated_cells(block.shape, rotation);
+        // Try each cell of the shape on the lowest-left cell of the block
+        for &(ox, oy) in &offsets {
+            let (x, y) = (cells[0].0 - ox, cells[0].1 - oy);
+            let mut placed: Vec<(i32, i32)> =
+                offsets.iter().map(|(dx, dy)| (x + dx, y + dy)).collect();
+            placed.sort();
+            if placed == cells {
+                return Ok((block.shape, rotation, x, y));
+            }
+        }
+    }
+    Err(invalid("piece is not a tetromino"))
+}
+
+fn cell_shape(value: u8) -> Option<BlockShape> {
+    match value {
+        1 => Some(BlockShape::Line),
+        2 => Some(BlockShape::L),
+        3 => Some(BlockShape::Square),
+        4 => Some(BlockShape::ZRev),
+        5 => Some(BlockShape::T),
+        6 => Some(BlockShape::LRev),
+        7 => Some(BlockShape::Z),
+        _ => None,
+    }
+}
+
+fn shape_cell(shape: BlockShape) -> u8 {
+    match shape {
+        BlockShape::Line => 1,
+        BlockShape::L => 2,
+        BlockShape::Square => 3,
+        BlockShape::ZRev => 4,
+        BlockShape::T => 5,
+        BlockShape::LRev => 6,
+        BlockShape::Z => 7,
+    }
+}
+
+fn field_board(field: &[u8; FIELD_CELLS]) -> Board {
+    let mut board = Board::new(FUMEN_WIDTH as usize, FUMEN_HEIGHT as usize);
+    for (index, &cell) in field
+        .iter()
+        .enumerate()
+        .take((FUMEN_HEIGHT * FUMEN_WIDTH) as usize)
+    {
+        if cell == 0 {
+            continue;
+        }
+        let point = Point::new(index as i32 % FUMEN_WIDTH, index as i32 / FUMEN_WIDTH);
+        let cell = cell_shape(cell).map_or(Cell::Garbage, Cell::Block);
+        board.cells.insert(point, cell);
+    }
+    board
+}
+
+fn board_field(board: &Board) -> Result<[u8; FIELD_CELLS], FumenError> {
+    if board.x_dim != FUMEN_WIDTH || board.y_dim != FUMEN_HEIGHT {
+        return Err(invalid("fumen boards are 10x23"));
+    }
+    let mut field = [0; FIELD_CELLS];
+    for (point, cell) in &board.cells {
+        let value = match cell {
+            Cell::Block(shape) => shape_cell(*shape),
+            Cell::Garbage => GARBAGE,
+        };
+        field[(point.get_y() * FUMEN_WIDTH + point.get_x()) as usize] = value;
+    }
+    Ok(field)
+}
+
+fn fill(field: &mut [u8; FIELD_CELLS], piece: &Block) {
+    for point in &piece.coordinates {
+        field[(point.get_y() * FUMEN_WIDTH + point.get_x()) as usize] = shape_cell(piece.shape);
+    }
+}
+
+/// Clears full rows of the visible field, leaving the garbage row alone.
+fn clear_lines(field: &mut [u8; FIELD_CELLS]) {
+    let width = FUMEN_WIDTH as usize;
+    let visible = FIELD_CELLS - width;
+    let mut rows: Vec<&[u8]> = field[..visible]
+        .chunks(width)
+        .filter(|row| row.contains(&0))
+        .collect();
+    let cleared = FUMEN_HEIGHT as usize - rows.len();
+    let empty = vec![0; width];
+    let mut kept: Vec<u8> = Vec::with_capacity(visible);
+    for _ in 0..cleared {
+        kept.extend_from_slice(&empty);
+    }
+    for row in rows.drain(..) {
+        kept.extend_from_slice(row);
+    }
+    field[..visible].copy_from_slice(&kept);
+}
+
+/// Run-length encodes the difference between two fields: each run is the
+/// cell difference (offset by 8) times the field size plus the run length
+/// minus one.
+fn field_runs(previous: &[u8; FIELD_CELLS], field: &[u8; FIELD_CELLS]) -> Vec<u32> {
+    let mut runs = Vec::new();
+    let mut index = 0;
+    while index < FIELD_CELLS {
+        let diff = |i: usize| field[i] as u32 + 8 - previous[i] as u32;
+        let value = diff(index);
+        let mut count = 1;
+        while index + count < FIELD_CELLS && diff(index + count) == value {
+            count += 1;
+        }
+        runs.push(value * FIELD_CELLS as u32 + count as u32 - 1);
+        index += count;
+    }
+    runs
+}
+
+/// Appends `value` as `count` base-64 digits, least significant first.
+fn push(digits: &mut Vec<u32>, mut value: u32, count: usize) {
+    for _ in 0..count {
+        digits.push(value % 64);
+        value /= 64;
+    }
+}
+
+struct Reader {
+    digits: Vec<u32>,
+    pos: usize,
+}
+
+impl Reader {
+    fn new(data: &str) -> Result<Reader, FumenError> {
+        let digits = data
+            .chars()
+            .filter(|&c| c != '?')
+            .map(|c| {
+                ENCODE_TABLE
+                    .iter()
+                    .position(|&e| e as char == c)
+                    .map(|d| d as u32)
+                    .ok_or_else(|| invalid(&format!("unexpected '{}'", c)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Reader { digits, pos: 0 })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.digits.len()
+    }
+
+    fn poll(&mut self, count: usize) -> Result<u32, FumenError> {
+        let digits = self
+            .digits
+            .get(self.pos..self.pos + count)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        self.pos += count;
+        Ok(digits.iter().rev().fold(0, |value, d| value * 64 + d))
+    }
+}
+
+/// Comments are stored escaped the way JavaScript's `escape` does it.
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+            out.push(c);
+        } else if (c as u32) < 256 {
+            out += &format!("%{:02X}", c as u32);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                out += &format!("%u{:04X}", unit);
+            }
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> String {
+    let mut units: Vec<u16> = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let hex = |from: usize, len: usize| {
+            let digits: String = chars.get(from..from + len)?.iter().collect();
+            u16::from_str_radix(&digits, 16).ok()
+        };
+        if chars[i] == '%' {
+            if chars.get(i + 1) == Some(&'u') {
+                if let Some(unit) = hex(i + 2, 4) {
+                    units.push(unit);
+                    i += 6;
+                    continue;
+                }
+            } else if let Some(unit) = hex(i + 1, 2) {
+                units.push(unit);
+                i += 3;
+                continue;
+            }
+        }
+        units.extend(chars[i].encode_utf16(&mut [0; 2]).iter());
+        i += 1;
+    }
+    String::from_utf16_lossy(&units)
+}
+
+impl Game {
+    /// The current position as a fumen page: the board, the falling block
+    /// and a quiz comment with the hold and next pieces.
+    pub fn fumen_page(&self) -> Result<Page, FumenError> {
+        if self.board.x_dim != FUMEN_WIDTH {
+            return Err(invalid("fumen boards are 10 wide"));
+        }
+        // Game rows are moved down so the floors line up
+        let shift = FUMEN_HEIGHT - self.board.y_dim;
+        let moved = |p: &Point| Point::new(p.get_x(), p.get_y() + shift);
+        let mut board = Board::new(FUMEN_WIDTH as usize, FUMEN_HEIGHT as usize);
+        for (point, cell) in &self.board.cells {
+            let target = moved(point);
+            if target.get_y() < 0 {
+                return Err(invalid("stack is higher than a fumen field"));
+            }
+            board.cells.insert(target, *cell);
+        }
+        let piece = self
+            .active_block()
+            .map(|block| Block {
+                shape: block.shape,
+                coordinates: block.coordinates.iter().map(moved).collect(),
+            })
+            .filter(|block| block.coordinates.iter().all(|p| p.get_y() >= 0));
+        let quiz = Quiz {
+            hold: self.hold,
+            current: self.active_block().map(|block| block.shape),
+            next: self.queue.iter().copied().collect(),
+        };
+        Ok(Page {
+            piece,
+            comment: quiz.to_string(),
+            ..Page::new(board)
+        })
+    }
+
+    /// Sets up the board, falling block, hold and next pieces from a fumen
+    /// page. The page's piece becomes the falling block where it stands, or
+    /// the quiz's current piece at the spawn point; the quiz's next pieces go
+    /// in front of the queue.
+    pub fn load_fumen_page(&mut self, page: &Page) -> Result<(), FumenError> {
+        if self.board.x_dim != FUMEN_WIDTH {
+            return Err(invalid("fumen boards are 10 wide"));
+        }
+        let shift = self.board.y_dim - FUMEN_HEIGHT;
+        let moved = |p: &Point| Point::new(p.get_x(), p.get_y() + shift);
+        let mut board = Board::new(self.board.x_dim as usize, self.board.y_dim as usize);
+        for (point, cell) in &page.board.cells {
+            let target = moved(point);
+            if target.get_y() < 0 {
+                return Err(invalid("the setup is higher than the board"));
+            }
+            board.cells.insert(target, *cell);
+        }
+        self.board = board;
+
+        let quiz = page.quiz();
+        if let Some(quiz) = &quiz {
+            self.hold = quiz.hold;
+            for shape in quiz.next.iter().rev() {
+                self.queue.push_front(*shape);
+            }
+        }
+        let piece = page.piece.as_ref().map(|block| Block {
+            shape: block.shape,
+            coordinates: block.coordinates.iter().map(moved).collect(),
+        });
+        match piece {
+            Some(block)
+                if block.coordinates.iter().all(|p| p.get_y() >= 0)
+                    && !self.board.block_touches(&block) =>
+            {
+                self.current_block = block
+            }
+            Some(block) => self.spawn(block.shape),
+            None => {
+                if let Some(shape) = quiz.and_then(|quiz| quiz.current) {
+                    self.spawn(shape);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameConfig;
+
+    fn empty() -> Page {
+        Page::new(Board::new(10, 23))
+    }
+
+    #[test]
+    fn test_empty_fumen() {
+        let pages = decode("v115@vhAAgH").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].board.cells.is_empty());
+        assert!(pages[0].piece.is_none());
+
+        assert_eq!(encode(&pages).unwrap(), "v115@vhAAgH");
+    }
+
+    #[test]
+    fn test_garbage_field() {
+        let pages = decode("https://fumen.zui.jp/?v115@bhI8KeAgH").unwrap();
+        assert_eq!(pages[0].board.rows()[22], "#########.");
+        assert_eq!(pages[0].board.cells.len(), 9);
+
+        assert_eq!(encode(&pages).unwrap(), "v115@bhI8KeAgH");
+    }
+
+    #[test]
+    fn test_piece_round_trip() {
+        for shape in [
+            BlockShape::Line,
+            BlockShape::Square,
+            BlockShape::T,
+            BlockShape::L,
+            BlockShape::LRev,
+            BlockShape::Z,
+            BlockShape::ZRev,
+        ] {
+            for rotation in 0..4 {
+                let block = piece_block(shape, rotation, 4, 2).unwrap();
+                let (_, turned, x, y) = piece_operation(&block).unwrap();
+                let mut cells = piece_block(shape, turned, x, y).unwrap().coordinates;
+                let mut expected = block.coordinates.clone();
+                cells.sort();
+                expected.sort();
+                assert_eq!(cells, expected);
+
+                let pages = vec![Page {
+                    piece: Some(block),
+                    ..empty()
+                }];
+                let data = encode(&pages).unwrap();
+                let mut decoded = decode(&data).unwrap()[0].piece.clone().unwrap().coordinates;
+                decoded.sort();
+                assert_eq!(decoded, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_page_locks_pieces() {
+        // A flat T on the floor, then an I beside it
+        let t = piece_block(BlockShape::T, 2, 1, 0).unwrap();
+        let i = piece_block(BlockShape::Line, 2, 5, 0).unwrap();
+        let first = Page {
+            piece: Some(t),
+            comment: "#Q=[](T)IO".to_string(),
+            ..empty()
+        };
+        let mut second = empty();
+        second.board.place_block(first.piece.as_ref().unwrap());
+        second.piece = Some(i);
+        second.comment = "#Q=[](I)O".to_string();
+        let pages = vec![first, second];
+
+        let data = encode(&pages).unwrap();
+        let decoded = decode(&data).unwrap();
+        assert_eq!(decoded, pages);
+        assert_eq!(
+            decoded[1].quiz(),
+            Some(Quiz {
+                hold: None,
+                current: Some(BlockShape::Line),
+                next: vec![BlockShape::Square],
+            })
+        );
+        // A third, empty page shows both pieces locked
+        let mut third = Page::new(decoded[1].board.clone());
+        third.board.place_block(decoded[1].piece.as_ref().unwrap());
+        third.comment = "#Q=[](O)".to_string();
+        let pages = [pages, vec![third]].concat();
+        assert_eq!(decode(&encode(&pages).unwrap()).unwrap(), pages);
+    }
+
+    #[test]
+    fn test_long_fumen_has_breaks() {
+        let mut page = empty();
+        page.comment = "a fairly long comment to push the data past the line length".to_string();
+        let data = encode(&[page.clone()]).unwrap();
+        assert!(data.contains('?'));
+        assert_eq!(decode(&data).unwrap(), vec![page]);
+    }
+
+    #[test]
+    fn test_comment_escaping() {
+        let text = "#Q=[T](I) 100% \u{3042}";
+        assert_eq!(escape("#Q=[T]"), "%23Q%3D%5BT%5D");
+        assert_eq!(unescape(&escape(text)), text);
+    }
+
+    #[test]
+    fn test_game_round_trip() {
+        let mut game = Game::new(GameConfig {
+            seed: 21,
+            ..GameConfig::default()
+        });
+        game.apply(crate::game::Action::HardDrop);
+        game.apply(crate::game::Action::Hold);
+        let data = encode(&[game.fumen_page().unwrap()]).unwrap();
+
+        let mut loaded = Game::new(GameConfig {
+            seed: 5,
+            ..GameConfig::default()
+        });
+        loaded.load_fumen_page(&decode(&data).unwrap()[0]).unwrap();
+        assert_eq!(loaded.board, game.board);
+        assert_eq!(loaded.hold, game.hold);
+        assert_eq!(loaded.current_block.shape, game.current_block.shape);
+        assert_eq!(
+            loaded
+                .queue
+                .iter()
+                .take(game.queue.len())
+                .collect::<Vec<_>>(),
+            game.queue.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_bad_fumens() {
+        assert!(decode("hello").is_err());
+        assert!(decode("v115@").is_err());
+        assert!(decode("v115@vh").is_err());
+        assert!(decode("v115@!!!").is_err());
+    }
+}
@@ -0,0 +1,1453 @@
+use crate::block::{Block, BlockShape};
+use crate::board::Board;
+use crate::finesse::{is_steering, Finesse};
+use crate::history::Snapshot;
+use crate::point::Point;
+use crate::puzzle::Puzzle;
+use crate::replay::{Replay, ReplayEvent};
+use crate::scoring::{t_spin, Clear, Grade, Spin, HARD_DROP_POINTS, SOFT_DROP_POINTS};
+use crate::search::route_to;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// The engine runs at a fixed rate; every timer in the game is counted in frames.
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+/// Lines to clear in sprint mode, and how often a split time is taken.
+pub const SPRINT_LINES: i32 = 40;
+pub const SPRINT_SPLIT_LINES: i32 = 10;
+
+/// Number of upcoming shapes shown to the player.
+pub const PREVIEW_LENGTH: usize = 3;
+
+/// The widest and tallest board a game accepts. Boards use `i32`
+/// coordinates, so this keeps sizes well inside their range.
+pub const MAX_BOARD_SIZE: usize = 1000;
+
+/// Frames between gravity steps for levels 1 to 15. Level 1 matches the
+/// original 500 ms drop interval.
+const GRAVITY_FRAMES: [u32; 15] = [30, 26, 22, 19, 16, 13, 11, 9, 7, 6, 5, 4, 3, 2, 1];
+
+/// Master mode runs from level 0 to this level.
+pub const MASTER_LEVELS: u32 = 999;
+
+/// Master mode gravity in 1/256 rows per frame, from each level on. 5120 is
+/// 20G.
+const MASTER_GRAVITY: [(u32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+/// Master mode ARE (frames before the next block appears) and lock delay,
+/// from each level on.
+const MASTER_DELAYS: [(u32, (u32, u32)); 4] = [
+    (0, (25, 30)),
+    (700, (16, 30)),
+    (800, (12, 30)),
+    (900, (12, 17)),
+];
+
+/// Checkpoints on the way to GM: the level, the score needed by then and the
+/// latest frame to reach it. The last one is the end of the game.
+const GM_CHECKPOINTS: [(u32, i32, u64); 3] = [
+    (300, 12000, (4 * 60 + 15) * FRAMES_PER_SECOND as u64),
+    (500, 40000, (7 * 60 + 30) * FRAMES_PER_SECOND as u64),
+    (
+        MASTER_LEVELS,
+        126000,
+        (13 * 60 + 30) * FRAMES_PER_SECOND as u64,
+    ),
+];
+
+/// How fast blocks fall: `rows` rows every `frames` frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gravity {
+    pub rows: u32,
+    pub frames: u32,
+}
+
+impl Gravity {
+    /// 20G and up: blocks reach the floor in the frame they appear.
+    pub fn is_instant(&self) -> bool {
+        self.rows >= 20 * self.frames
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Left,
+    Right,
+    SoftDrop,
+    Rotate,
+    HardDrop,
+    Hold,
+    /// Take back the last placement, outside the competitive modes.
+    Undo,
+    /// Put back a placement that was undone.
+    Redo,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::SoftDrop => "SoftDrop",
+            Action::Rotate => "Rotate",
+            Action::HardDrop => "HardDrop",
+            Action::Hold => "Hold",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "left" => Ok(Action::Left),
+            "right" => Ok(Action::Right),
+            "softdrop" => Ok(Action::SoftDrop),
+            "rotate" => Ok(Action::Rotate),
+            "harddrop" => Ok(Action::HardDrop),
+            "hold" => Ok(Action::Hold),
+            "undo" => Ok(Action::Undo),
+            "redo" => Ok(Action::Redo),
+            _ => Err(format!("unknown action '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum GameMode {
+    Endless,
+    /// Clear 40 lines as fast as possible.
+    Sprint,
+    /// Score as much as possible before the time limit.
+    Ultra,
+    /// Dig through a set number of garbage rows as fast as possible.
+    Dig,
+    /// Clear 150 lines through 15 levels of increasing speed.
+    Marathon,
+    /// Survive to level 999 as gravity climbs to 20G, for a grade up to GM.
+    Master,
+    /// A sandbox with board editing, chosen pieces, undo and optional gravity.
+    Practice,
+}
+
+impl GameMode {
+    /// Modes with personal bests, where placements can't be undone.
+    pub fn is_competitive(&self) -> bool {
+        !matches!(self, GameMode::Endless | GameMode::Practice)
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GameMode::Endless => "endless",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+            GameMode::Dig => "dig",
+            GameMode::Marathon => "marathon",
+            GameMode::Master => "master",
+            GameMode::Practice => "practice",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "endless" => Ok(GameMode::Endless),
+            "sprint" => Ok(GameMode::Sprint),
+            "ultra" => Ok(GameMode::Ultra),
+            "dig" => Ok(GameMode::Dig),
+            "marathon" => Ok(GameMode::Marathon),
+            "master" => Ok(GameMode::Master),
+            "practice" => Ok(GameMode::Practice),
+            _ => Err(format!("unknown mode '{}'", s)),
+        }
+    }
+}
+
+/// How upcoming shapes are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Randomizer {
+    /// Every shape is drawn independently.
+    Random,
+    /// Shapes come in shuffled bags of all seven, as in modern games.
+    Bag,
+}
+
+impl fmt::Display for Randomizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Randomizer::Random => "random",
+            Randomizer::Bag => "bag",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "random" => Ok(Randomizer::Random),
+            "bag" | "7-bag" => Ok(Randomizer::Bag),
+            _ => Err(format!("unknown randomizer '{}'", s)),
+        }
+    }
+}
+
+/// Everything needed to start a game. Two games built from equal configs and
+/// fed the same actions on the same frames play out identically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameConfig {
+    pub mode: GameMode,
+    pub seed: u64,
+    pub level: u32,
+    pub width: usize,
+    pub height: usize,
+    /// Length of an ultra game in seconds.
+    pub time_limit: u32,
+    /// Garbage rows to clear in dig mode.
+    pub garbage_lines: u32,
+    /// How many garbage rows are on the board at once in dig mode.
+    pub garbage_height: usize,
+    /// Percent chance that a garbage row's hole moves from the row below it.
+    pub messiness: u32,
+    /// Lines to clear in marathon mode, spread evenly over the 15 levels.
+    pub marathon_lines: u32,
+    /// Keep playing a marathon after its goal is reached.
+    pub marathon_endless: bool,
+    pub randomizer: Randomizer,
+    /// Keep a snapshot of the game for every placement so it can be undone.
+    /// Only the interactive game turns this on: bots never undo, and the
+    /// snapshots would pile up over the long games they play.
+    pub undo: bool,
+    /// Judge the finesse of every placement. Working out the fewest presses
+    /// means a search per lock, which only a person watching needs.
+    pub finesse: bool,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            mode: GameMode::Endless,
+            seed: rand::random(),
+            level: 1,
+            width: 10,
+            height: 20,
+            time_limit: 120,
+            garbage_lines: 10,
+            garbage_height: 10,
+            messiness: 100,
+            marathon_lines: 150,
+            marathon_endless: false,
+            randomizer: Randomizer::Random,
+            undo: false,
+            finesse: false,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Checks that the board is large enough to spawn every shape and no
+    /// larger than [`MAX_BOARD_SIZE`], that the level is within the gravity table and, in dig mode, that the garbage
+    /// fits on the board.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width < 4 || self.height < 4 {
+            return Err(format!(
+                "board must be at least 4x4, got {}x{}",
+                self.width, self.height
+            ));
+        }
+        if self.width > MAX_BOARD_SIZE || self.height > MAX_BOARD_SIZE {
+            return Err(format!(
+                "board must be at most {}x{}, got {}x{}",
+                MAX_BOARD_SIZE, MAX_BOARD_SIZE, self.width, self.height
+            ));
+        }
+        if self.level == 0 || self.level > MAX_LEVEL {
+            return Err(format!(
+                "level must be between 1 and {}, got {}",
+                MAX_LEVEL, self.level
+            ));
+        }
+        if self.time_limit == 0 {
+            return Err("time limit must be at least one second".to_string());
+        }
+        if self.mode == GameMode::Dig {
+            if self.garbage_lines == 0 {
+                return Err("dig mode needs at least one garbage line".to_string());
+            }
+            if self.garbage_height == 0 || self.garbage_height >= self.height - 2 {
+                return Err(format!(
+                    "garbage height must be between 1 and {}, got {}",
+                    self.height - 3,
+                    self.garbage_height
+                ));
+            }
+        }
+        if self.mode == GameMode::Marathon && self.marathon_lines == 0 {
+            return Err("marathon needs at least one line".to_string());
+        }
+        if self.messiness > 100 {
+            return Err(format!("messiness is a percentage, got {}", self.messiness));
+        }
+        Ok(())
+    }
+
+    /// Sets a single field by its name, as written in `key=value` lists, or
+    /// by its long command-line option, as used in config files.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "mode" => self.mode = value.parse()?,
+            "seed" => self.seed = parse_number(key, value)?,
+            "level" => self.level = parse_number(key, value)?,
+            "width" => self.width = parse_number(key, value)?,
+            "height" => self.height = parse_number(key, value)?,
+            "time_limit" | "time-limit" => self.time_limit = parse_number(key, value)?,
+            "garbage_lines" | "garbage-lines" => self.garbage_lines = parse_number(key, value)?,
+            "garbage_height" | "garbage-height" => self.garbage_height = parse_number(key, value)?,
+            "messiness" => self.messiness = parse_number(key, value)?,
+            "marathon_lines" | "marathon-lines" => self.marathon_lines = parse_number(key, value)?,
+            "marathon_endless" | "endless-after-goal" => {
+                self.marathon_endless = parse_number(key, value)?
+            }
+            "randomizer" => self.randomizer = value.parse()?,
+            "undo" => self.undo = parse_number(key, value)?,
+            "finesse" => self.finesse = parse_number(key, value)?,
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+}
+
+fn parse_number<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, key))
+}
+
+/// Formats as space-separated `key=value` pairs, the form [`FromStr`] reads
+/// back.
+impl fmt::Display for GameConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mode={} seed={} level={} width={} height={} time_limit={} \
+             garbage_lines={} garbage_height={} messiness={} \
+             marathon_lines={} marathon_endless={} randomizer={} undo={} finesse={}",
+            self.mode,
+            self.seed,
+            self.level,
+            self.width,
+            self.height,
+            self.time_limit,
+            self.garbage_lines,
+            self.garbage_height,
+            self.messiness,
+            self.marathon_lines,
+            self.marathon_endless,
+            self.randomizer,
+            self.undo,
+            self.finesse
+        )
+    }
+}
+
+/// Parses `key=value` pairs; missing keys keep their defaults.
+impl FromStr for GameConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = GameConfig::default();
+        for pair in s.split_whitespace() {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+            config.set(key, value)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Converts a frame count to milliseconds of game time.
+pub fn frames_to_millis(frames: u64) -> u64 {
+    frames * 1000 / FRAMES_PER_SECOND as u64
+}
+
+/// Highest level; gravity stops getting faster here.
+pub const MAX_LEVEL: u32 = GRAVITY_FRAMES.len() as u32;
+
+/// Frames between gravity steps at the given level; levels past the end of
+/// the table keep the fastest speed.
+pub fn gravity_frames(level: u32) -> u32 {
+    let index = (level.max(1) as usize - 1).min(GRAVITY_FRAMES.len() - 1);
+    GRAVITY_FRAMES[index]
+}
+
+/// A game in progress. Play goes through [`Game::apply`] for player input and
+/// [`Game::tick`] for time, so outside practice mode a config and the actions
+/// applied on each frame replay it exactly.
+pub struct Game {
+    pub config: GameConfig,
+    pub board: Board,
+    pub current_block: Block,
+    /// Upcoming shapes, next first.
+    pub queue: VecDeque<BlockShape>,
+    pub hold: Option<BlockShape>,
+    /// Hold may be used once per piece; reset when a block locks.
+    pub can_hold: bool,
+    pub score: i32,
+    pub lines: i32,
+    pub level: u32,
+    pub frame: u64,
+    pub game_over: bool,
+    /// Set when the mode's goal has been reached.
+    pub finished: bool,
+    /// Frames at which each split was reached: every 10 lines in sprint mode
+    /// and every 100 levels in master mode.
+    pub splits: Vec<u64>,
+    /// Number of blocks locked so far.
+    pub pieces: u32,
+    /// Consecutive line-clearing locks minus one; -1 when no combo is running.
+    pub combo: i32,
+    pub best_combo: i32,
+    /// Whether the last line clear was a Tetris or T-spin.
+    pub back_to_back: bool,
+    /// The most recent lock that scored, for display.
+    pub last_clear: Option<Clear>,
+    /// Whether the block's last successful move was a rotation, which makes
+    /// it eligible for a T-spin.
+    pub(crate) last_rotated: bool,
+    /// Garbage rows pushed onto the board so far (dig mode).
+    pub garbage_added: u32,
+    pub(crate) rng: ChaCha8Rng,
+    /// Garbage has its own generator so the piece sequence for a seed is the
+    /// same in every mode.
+    pub(crate) garbage_rng: ChaCha8Rng,
+    pub(crate) garbage_hole: i32,
+    pub(crate) gravity_counter: u32,
+    /// Frames the block has spent on the ground without moving down.
+    pub(crate) lock_counter: u32,
+    /// Frames left before the next block appears; no block is active meanwhile.
+    pub(crate) are: u32,
+    /// Whether every GM checkpoint so far was reached in time (master mode).
+    pub(crate) gm_eligible: bool,
+    /// The puzzle being played, as it was at the start.
+    pub puzzle: Option<Puzzle>,
+    /// Practice mode can turn gravity off.
+    pub gravity_enabled: bool,
+    pub(crate) undo_stack: Vec<Snapshot>,
+    pub(crate) redo_stack: Vec<Snapshot>,
+    pub(crate) piece_start: Option<Snapshot>,
+    /// Finesse of the last placement, and how many placements took more
+    /// presses than needed.
+    pub finesse: Option<Finesse>,
+    pub finesse_faults: u32,
+    /// The current block as it appeared and the steering presses spent on
+    /// it since.
+    pub(crate) spawned: Block,
+    pub(crate) presses: u32,
+    pub(crate) events: Vec<ReplayEvent>,
+}
+
+impl Game {
+    /// Starts a game with `config`, its first piece already spawned.
+    pub fn new(config: GameConfig) -> Game {
+        Game::start(config, None)
+    }
+
+    /// Starts a game, from a puzzle's board and piece sequence when one is
+    /// given. Puzzles are played by endless mode rules on a board the size of
+    /// the puzzle's.
+    pub fn start(mut config: GameConfig, puzzle: Option<Puzzle>) -> Game {
+        if let Some(puzzle) = &puzzle {
+            config.mode = GameMode::Endless;
+            config.width = puzzle.board.x_dim as usize;
+            config.height = puzzle.board.y_dim as usize;
+        }
+        let rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let mut garbage_rng = ChaCha8Rng::seed_from_u64(config.seed);
+        garbage_rng.set_stream(1);
+        let board = match &puzzle {
+            Some(puzzle) => puzzle.board.clone(),
+            None => Board::new(config.width, config.height),
+        };
+        let queue = match &puzzle {
+            Some(puzzle) => puzzle.pieces.iter().copied().collect(),
+            None => VecDeque::with_capacity(PREVIEW_LENGTH + 1),
+        };
+        let placeholder = Block::new(spawn_point(&board), BlockShape::Square);
+
+        let level = match config.mode {
+            GameMode::Master => 0,
+            _ => config.level,
+        };
+        let mut game = Game {
+            level,
+            config,
+            board,
+            current_block: placeholder.clone(),
+            queue,
+            hold: None,
+            can_hold: true,
+            score: 0,
+            lines: 0,
+            frame: 0,
+            game_over: false,
+            finished: false,
+            splits: Vec::new(),
+            pieces: 0,
+            combo: -1,
+            best_combo: 0,
+            back_to_back: false,
+            last_clear: None,
+            last_rotated: false,
+            garbage_added: 0,
+            rng,
+            garbage_rng,
+            garbage_hole: 0,
+            gravity_counter: 0,
+            lock_counter: 0,
+            are: 0,
+            gm_eligible: true,
+            puzzle,
+            gravity_enabled: true,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            piece_start: None,
+            finesse: None,
+            finesse_faults: 0,
+            spawned: placeholder,
+            presses: 0,
+            events: Vec::new(),
+        };
+        if game.config.mode == GameMode::Dig {
+            game.refill_garbage();
+        }
+        game.spawn_next();
+        game
+    }
+
+    /// Everything the player has done so far, enough to replay the game.
+    pub fn replay(&self) -> Replay {
+        Replay {
+            config: self.config.clone(),
+            puzzle: self.puzzle.clone(),
+            frames: self.frame,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Whether the game has stopped, by topping out or by reaching the goal.
+    pub fn ended(&self) -> bool {
+        self.game_over || self.finished
+    }
+
+    /// The current block as it appeared, before it was moved.
+    pub fn spawned(&self) -> &Block {
+        &self.spawned
+    }
+
+    /// The falling block, or `None` while waiting for the next one to appear.
+    pub fn active_block(&self) -> Option<&Block> {
+        if self.are > 0 {
+            None
+        } else {
+            Some(&self.current_block)
+        }
+    }
+
+    /// The falling speed at the current level.
+    pub fn gravity(&self) -> Gravity {
+        match self.config.mode {
+            GameMode::Master => Gravity {
+                rows: master_timing(&MASTER_GRAVITY, self.level),
+                frames: 256,
+            },
+            _ => Gravity {
+                rows: 1,
+                frames: gravity_frames(self.level),
+            },
+        }
+    }
+
+    /// Frames a grounded block waits before locking. Without a lock delay a
+    /// block locks as soon as gravity can't move it.
+    pub fn lock_delay(&self) -> Option<u32> {
+        match self.config.mode {
+            GameMode::Master => Some(master_timing(&MASTER_DELAYS, self.level).1),
+            _ => None,
+        }
+    }
+
+    /// Frames between a block locking and the next one appearing.
+    pub fn spawn_delay(&self) -> u32 {
+        match self.config.mode {
+            GameMode::Master => master_timing(&MASTER_DELAYS, self.level).0,
+            _ => 0,
+        }
+    }
+
+    /// The master mode grade earned so far.
+    pub fn grade(&self) -> Grade {
+        let (level, score, frame) = GM_CHECKPOINTS[GM_CHECKPOINTS.len() - 1];
+        if self.gm_eligible && self.level >= level && self.score >= score && self.frame <= frame {
+            Grade::GM
+        } else {
+            Grade::from_score(self.score)
+        }
+    }
+
+    /// Applies a player action to the current block. Returns whether the
+    /// block moved.
+    pub fn apply(&mut self, action: Action) -> bool {
+        if let Action::Undo | Action::Redo = action {
+            return self.step_history(action);
+        }
+        if self.ended() || self.are > 0 {
+            return false;
+        }
+        self.events.push(ReplayEvent {
+            frame: self.frame,
+            action,
+        });
+        if is_steering(action) {
+            self.presses += 1;
+        }
+        match action {
+            Action::HardDrop => return self.hard_drop(),
+            Action::Hold => return self.hold(),
+            _ => {}
+        }
+        match moved_block(&self.board, &self.current_block, action) {
+            Some(block) => {
+                self.current_block = block;
+                self.last_rotated = action == Action::Rotate;
+                if action == Action::SoftDrop {
+                    self.score += SOFT_DROP_POINTS;
+                    self.lock_counter = 0;
+                } else if self.gravity().is_instant() {
+                    self.fall(u32::MAX);
+                }
+                true
+            }
+            // With a lock delay, soft dropping a grounded block locks it
+            _ if action == Action::SoftDrop && self.lock_delay().is_some() => {
+                self.lock();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Advances the game by one frame, applying gravity when it is due.
+    pub fn tick(&mut self) {
+        if self.ended() {
+            return;
+        }
+        self.frame += 1;
+        if self.config.mode == GameMode::Ultra
+            && self.frame >= self.config.time_limit as u64 * FRAMES_PER_SECOND as u64
+        {
+            self.finished = true;
+            return;
+        }
+        if self.are > 0 {
+            self.are -= 1;
+            if self.are == 0 {
+                self.spawn_next();
+            }
+            return;
+        }
+        if !self.gravity_enabled {
+            return;
+        }
+
+        let gravity = self.gravity();
+        self.gravity_counter += gravity.rows;
+        let rows = self.gravity_counter / gravity.frames;
+        self.gravity_counter %= gravity.frames;
+
+        match self.lock_delay() {
+            Some(delay) => {
+                self.fall(rows);
+                if self.grounded() {
+                    self.lock_counter += 1;
+                    if self.lock_counter >= delay {
+                        self.lock();
+                    }
+                }
+            }
+            None if rows > 0 && !self.fall(rows) => self.lock(),
+            None => {}
+        }
+    }
+
+    /// Moves the block down by up to `rows` rows. Returns whether it moved.
+    fn fall(&mut self, rows: u32) -> bool {
+        let mut moved = false;
+        for _ in 0..rows {
+            match self.shifted(0, 1) {
+                Some(block) if !self.board.block_touches(&block) => {
+                    self.current_block = block;
+                    self.last_rotated = false;
+                    self.lock_counter = 0;
+                    moved = true;
+                }
+                _ => break,
+            }
+        }
+        moved
+    }
+
+    /// Whether the block is resting on the floor or the stack.
+    fn grounded(&self) -> bool {
+        match self.shifted(0, 1) {
+            Some(block) => self.board.block_touches(&block),
+            None => true,
+        }
+    }
+
+    fn shifted(&self, dx: i32, dy: i32) -> Option<Block> {
+        self.current_block
+            .translate(dx, dy, self.board.x_dim, self.board.y_dim)
+    }
+
+    /// Drops the current block as far as it goes and locks it at once.
+    fn hard_drop(&mut self) -> bool {
+        while let Some(block) = self.shifted(0, 1) {
+            if self.board.block_touches(&block) {
+                break;
+            }
+            self.current_block = block;
+            self.last_rotated = false;
+            self.score += HARD_DROP_POINTS;
+        }
+        self.lock();
+        true
+    }
+
+    /// Swaps the current block with the held shape, or with the next shape
+    /// when nothing is held yet.
+    fn hold(&mut self) -> bool {
+        if !self.can_hold {
+            return false;
+        }
+        let shape = match self.hold {
+            Some(held) => held,
+            None => match self.next_shape() {
+                Some(shape) => shape,
+                None => return false,
+            },
+        };
+        self.hold = Some(self.current_block.shape);
+        self.spawn(shape);
+        self.can_hold = false;
+        true
+    }
+
+    /// Places the current block, clears completed rows, scores the lock and
+    /// spawns the next block.
+    fn lock(&mut self) {
+        let spin = if self.last_rotated {
+            t_spin(&self.board, &self.current_block)
+        } else {
+            Spin::None
+        };
+        self.remember_placement();
+        if self.config.finesse {
+            self.judge_finesse();
+        }
+        self.board.place_block(&self.current_block);
+        let cleared = self.board.clear_board();
+        self.pieces += 1;
+        self.score_lock(cleared, spin);
+        self.add_lines(cleared);
+        if self.config.mode == GameMode::Master {
+            self.advance_master_level(cleared);
+        }
+        if let Some(puzzle) = &self.puzzle {
+            if puzzle.goal.met(self, cleared, spin) {
+                self.finished = true;
+            } else if self.pieces >= puzzle.limit {
+                self.game_over = true;
+                return;
+            }
+        }
+        if self.config.mode == GameMode::Dig {
+            if self.garbage_cleared() >= self.config.garbage_lines {
+                self.finished = true;
+            } else {
+                self.refill_garbage();
+            }
+        }
+        if self.finished {
+            return;
+        }
+        if self.board.overflowed() {
+            self.game_over = true;
+            return;
+        }
+
+        match self.spawn_delay() {
+            0 => self.spawn_next(),
+            delay => self.are = delay,
+        }
+    }
+
+    /// Spawns the next shape, or the held one once a puzzle's sequence has
+    /// run out. With nothing left the game is over.
+    fn spawn_next(&mut self) {
+        match self.next_shape().or_else(|| self.hold.take()) {
+            Some(shape) => {
+                self.spawn(shape);
+                self.can_hold = true;
+                self.remember_spawn();
+            }
+            None => self.game_over = true,
+        }
+    }
+
+    fn score_lock(&mut self, cleared: i32, spin: Spin) {
+        let mut back_to_back = false;
+        if cleared > 0 {
+            self.combo += 1;
+            self.best_combo = self.best_combo.max(self.combo);
+            let difficult = Clear::is_difficult(cleared, spin);
+            back_to_back = difficult && self.back_to_back;
+            self.back_to_back = difficult;
+        } else {
+            self.combo = -1;
+        }
+
+        if cleared == 0 && spin == Spin::None {
+            return;
+        }
+        let clear = Clear {
+            lines: cleared,
+            spin,
+            back_to_back,
+            combo: self.combo.max(0),
+            perfect: cleared > 0 && self.board.cells.is_empty(),
+        };
+        self.score += match self.config.mode {
+            GameMode::Master => clear.master_points(self.level),
+            _ => clear.points(self.level),
+        };
+        self.last_clear = Some(clear);
+    }
+
+    /// Counts cleared lines towards the mode's goal.
+    fn add_lines(&mut self, cleared: i32) {
+        let previous = self.lines;
+        self.lines += cleared;
+
+        if self.config.mode == GameMode::Sprint {
+            let reached = self.lines.min(SPRINT_LINES) / SPRINT_SPLIT_LINES;
+            for _ in previous / SPRINT_SPLIT_LINES..reached {
+                self.splits.push(self.frame);
+            }
+            if self.lines >= SPRINT_LINES {
+                self.finished = true;
+            }
+        }
+
+        if self.config.mode == GameMode::Marathon {
+            let per_level = (self.config.marathon_lines / MAX_LEVEL).max(1);
+            let gained = self.lines as u32 / per_level;
+            self.level = (self.config.level + gained).min(MAX_LEVEL);
+            if self.marathon_complete() && !self.config.marathon_endless {
+                self.finished = true;
+            }
+        }
+    }
+
+    /// Master mode levels go up by one per block and by one per cleared line,
+    /// but a block alone can't take the level past the end of a section
+    /// (x99 or 998). Passing a GM checkpoint late or short of points rules out
+    /// the GM grade.
+    fn advance_master_level(&mut self, cleared: i32) {
+        let previous = self.level;
+        let stopped = self.level % 100 == 99 || self.level == MASTER_LEVELS - 1;
+        let gained = cleared as u32 + if stopped { 0 } else { 1 };
+        self.level = (self.level + gained).min(MASTER_LEVELS);
+
+        for _ in previous / 100..self.level / 100 {
+            self.splits.push(self.frame);
+        }
+        for &(level, score, frame) in &GM_CHECKPOINTS {
+            if previous < level && self.level >= level && (self.score < score || self.frame > frame)
+            {
+                self.gm_eligible = false;
+            }
+        }
+        if self.level >= MASTER_LEVELS {
+            self.finished = true;
+        }
+    }
+
+    /// Whether the marathon goal has been reached; play may continue past it.
+    pub fn marathon_complete(&self) -> bool {
+        self.config.mode == GameMode::Marathon && self.lines as u32 >= self.config.marathon_lines
+    }
+
+    /// Garbage rows cleared so far (dig mode).
+    pub fn garbage_cleared(&self) -> u32 {
+        self.garbage_added - self.board.garbage_rows() as u32
+    }
+
+    /// Tops the garbage back up to its configured height, without adding
+    /// more rows in total than the player has to clear.
+    fn refill_garbage(&mut self) {
+        while self.board.garbage_rows() < self.config.garbage_height
+            && self.garbage_added < self.config.garbage_lines
+        {
+            let width = self.board.x_dim;
+            let moves = self.garbage_rng.gen_range(0..100) < self.config.messiness;
+            if self.garbage_added == 0 {
+                self.garbage_hole = self.garbage_rng.gen_range(0..width);
+            } else if moves {
+                // Pick a different column so the hole really moves
+                let step = self.garbage_rng.gen_range(1..width);
+                self.garbage_hole = (self.garbage_hole + step) % width;
+            }
+            self.board.insert_garbage_row(&[self.garbage_hole]);
+            self.garbage_added += 1;
+        }
+    }
+
+    /// Takes the next shape from the queue, keeping the preview topped up.
+    /// Puzzles only have their own sequence, so this runs out.
+    fn next_shape(&mut self) -> Option<BlockShape> {
+        self.fill_queue(PREVIEW_LENGTH + 1);
+        self.queue.pop_front()
+    }
+
+    /// Adds random shapes until the queue holds at least `length`, except in
+    /// puzzles. The bag randomizer adds a whole bag at a time.
+    pub(crate) fn fill_queue(&mut self, length: usize) {
+        while self.puzzle.is_none() && self.queue.len() < length {
+            match self.config.randomizer {
+                Randomizer::Random => {
+                    let shape: BlockShape = self.rng.gen();
+                    self.queue.push_back(shape);
+                }
+                Randomizer::Bag => {
+                    let mut bag: Vec<BlockShape> = BlockShape::iter().collect();
+                    bag.shuffle(&mut self.rng);
+                    self.queue.extend(bag);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn spawn(&mut self, shape: BlockShape) {
+        self.current_block = Block::new(spawn_point(&self.board), shape);
+        self.gravity_counter = 0;
+        self.lock_counter = 0;
+        if self.board.block_touches(&self.current_block) {
+            self.game_over = true;
+        } else if self.gravity().is_instant() {
+            self.fall(u32::MAX);
+        }
+        self.spawned = self.current_block.clone();
+        self.presses = 0;
+    }
+
+    /// Compares the steering presses spent on the current block with the
+    /// fewest that would have put it where it is.
+    fn judge_finesse(&mut self) {
+        let Some(inputs) = route_to(
+            &self.board,
+            &self.spawned,
+            &self.current_block,
+            self.gravity().is_instant(),
+        ) else {
+            return;
+        };
+        let finesse = Finesse {
+            presses: self.presses,
+            minimum: inputs.iter().filter(|action| is_steering(**action)).count() as u32,
+        };
+        if finesse.is_fault() {
+            self.finesse_faults += 1;
+        }
+        self.finesse = Some(finesse);
+    }
+}
+
+/// The entry of a master mode timing table in effect at `level`.
+fn master_timing<T: Copy>(table: &[(u32, T)], level: u32) -> T {
+    table
+        .iter()
+        .rev()
+        .find(|entry| entry.0 <= level)
+        .map_or(table[0].1, |entry| entry.1)
+}
+
+/// Where `action` moves `block` on `board`, or `None` if the way is blocked.
+/// Only moves and rotations are handled; rotations have no wall kicks.
+pub fn moved_block(board: &Board, block: &Block, action: Action) -> Option<Block> {
+    let moved = match action {
+        Action::Left => block.translate(-1, 0, board.x_dim, board.y_dim),
+        Action::Right => block.translate(1, 0, board.x_dim, board.y_dim),
+        Action::SoftDrop => block.translate(0, 1, board.x_dim, board.y_dim),
+        // Rotating next to the right wall can push cells past it
+        Action::Rotate => block
+            .rotate()
+            .ok()
+            .filter(|block| block.coordinates.iter().all(|p| p.get_x() < board.x_dim)),
+        _ => None,
+    }?;
+    (!board.block_touches(&moved)).then_some(moved)
+}
+
+/// New blocks appear at the top, just left of the centre column.
+pub(crate) fn spawn_point(board: &Board) -> Point {
+    Point::new(board.x_dim / 2 - 1, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+
+    fn config(seed: u64) -> GameConfig {
+        GameConfig {
+            seed,
+            ..GameConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let mut a = Game::new(config(7));
+        let mut b = Game::new(config(7));
+
+        for frame in 0..2000 {
+            if frame % 5 == 0 {
+                a.apply(Action::Left);
+                b.apply(Action::Left);
+            }
+            a.tick();
+            b.tick();
+        }
+
+        assert_eq!(a.board, b.board);
+        assert_eq!(a.current_block.coordinates, b.current_block.coordinates);
+        assert_eq!(a.score, b.score);
+    }
+
+    #[test]
+    fn test_gravity_follows_level() {
+        let mut game = Game::new(GameConfig {
+            level: 3,
+            ..config(1)
+        });
+        let start = game.current_block.coordinates.clone();
+
+        for _ in 0..gravity_frames(3) - 1 {
+            game.tick();
+        }
+        assert_eq!(game.current_block.coordinates, start);
+
+        game.tick();
+        assert_eq!(
+            game.current_block.coordinates[0].get_y(),
+            start[0].get_y() + 1
+        );
+    }
+
+    #[test]
+    fn test_moves_blocked_by_walls() {
+        let mut game = Game::new(config(3));
+        while game.apply(Action::Left) {}
+
+        let min_x = game
+            .current_block
+            .coordinates
+            .iter()
+            .map(|p| p.get_x())
+            .min();
+        assert_eq!(min_x, Some(0));
+    }
+
+    #[test]
+    fn test_hold_swaps_once_per_piece() {
+        let mut game = Game::new(config(5));
+        let first = game.current_block.shape;
+        let next = game.queue[0];
+
+        assert!(game.apply(Action::Hold));
+        assert_eq!(game.hold, Some(first));
+        assert_eq!(game.current_block.shape, next);
+        assert_eq!(game.queue.len(), PREVIEW_LENGTH);
+
+        // A second hold before the block locks is refused
+        assert!(!game.apply(Action::Hold));
+        assert_eq!(game.current_block.shape, next);
+    }
+
+    #[test]
+    fn test_hard_drop_locks_at_bottom() {
+        let mut game = Game::new(config(2));
+
+        assert!(game.apply(Action::HardDrop));
+        let bottom = game.board.cells.keys().map(|p| p.get_y()).max();
+        assert_eq!(bottom, Some(game.board.y_dim - 1));
+        assert_eq!(game.board.cells.len(), 4);
+
+        // The next block spawns at the top straight away
+        let top = game
+            .current_block
+            .coordinates
+            .iter()
+            .map(|p| p.get_y())
+            .min();
+        assert_eq!(top, Some(0));
+    }
+
+    #[test]
+    fn test_sprint_splits_and_finish() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Sprint,
+            ..config(4)
+        });
+        game.frame = 100;
+        game.add_lines(4);
+        game.frame = 200;
+        game.add_lines(8);
+        assert_eq!(game.splits, vec![200]);
+        assert!(!game.finished);
+
+        game.frame = 300;
+        game.add_lines(30);
+        assert_eq!(game.splits, vec![200, 300, 300, 300]);
+        assert!(game.finished);
+        assert!(game.ended());
+    }
+
+    #[test]
+    fn test_ultra_ends_at_time_limit() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Ultra,
+            time_limit: 2,
+            ..config(6)
+        });
+        for _ in 0..2 * FRAMES_PER_SECOND - 1 {
+            game.tick();
+        }
+        assert!(!game.ended());
+
+        game.tick();
+        assert!(game.finished);
+        assert_eq!(game.frame, 2 * FRAMES_PER_SECOND as u64);
+    }
+
+    #[test]
+    fn test_combo_and_back_to_back() {
+        let mut game = Game::new(config(8));
+        // Leave a cell behind so the clears are not perfect clears
+        game.board.cells.insert(Point::new(0, 19), Cell::Garbage);
+        game.score_lock(4, Spin::None);
+        game.score_lock(4, Spin::None);
+        assert_eq!(game.combo, 1);
+        assert_eq!(game.score, 800 + 1200 + 50);
+        assert!(game.last_clear.unwrap().back_to_back);
+
+        game.score_lock(0, Spin::None);
+        assert_eq!(game.combo, -1);
+        assert_eq!(game.best_combo, 1);
+
+        // A single breaks the back-to-back chain
+        game.score_lock(1, Spin::None);
+        assert!(!game.back_to_back);
+    }
+
+    #[test]
+    fn test_dig_fills_and_refills_garbage() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Dig,
+            garbage_lines: 12,
+            garbage_height: 5,
+            messiness: 0,
+            ..config(10)
+        });
+        assert_eq!(game.board.garbage_rows(), 5);
+        assert_eq!(game.garbage_added, 5);
+        // With no messiness every hole is in the same column
+        let holes: Vec<i32> = (0..game.board.x_dim)
+            .filter(|&x| game.board.cell(&Point::new(x, 19)).is_none())
+            .collect();
+        assert_eq!(holes.len(), 1);
+        assert!(game.board.cell(&Point::new(holes[0], 15)).is_none());
+
+        // Clear the bottom row by filling its hole
+        game.board
+            .cells
+            .insert(Point::new(holes[0], 19), Cell::Garbage);
+        game.board
+            .cells
+            .insert(Point::new(holes[0], 18), Cell::Garbage);
+        game.current_block = Block::new(Point::new(0, 8), BlockShape::Square);
+        game.lock();
+        assert!(game.garbage_cleared() >= 2);
+        assert_eq!(game.board.garbage_rows(), 5);
+        assert!(!game.ended());
+    }
+
+    #[test]
+    fn test_marathon_levels_and_goal() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Marathon,
+            ..config(12)
+        });
+        game.add_lines(9);
+        assert_eq!(game.level, 1);
+        game.add_lines(2);
+        assert_eq!(game.level, 2);
+
+        game.add_lines(138);
+        assert_eq!(game.level, MAX_LEVEL);
+        assert!(!game.finished);
+        game.add_lines(1);
+        assert!(game.marathon_complete());
+        assert!(game.finished);
+    }
+
+    #[test]
+    fn test_marathon_endless_continues() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Marathon,
+            marathon_lines: 30,
+            marathon_endless: true,
+            ..config(12)
+        });
+        game.add_lines(40);
+        assert!(game.marathon_complete());
+        assert!(!game.ended());
+        assert_eq!(game.level, 15);
+    }
+
+    fn master(level: u32) -> Game {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Master,
+            ..config(13)
+        });
+        game.level = level;
+        game
+    }
+
+    #[test]
+    fn test_master_gravity_accumulates() {
+        // 64/256 rows per frame: one row every four frames
+        let mut game = master(90);
+        let start = game.current_block.coordinates[0].get_y();
+        for _ in 0..3 {
+            game.tick();
+        }
+        assert_eq!(game.current_block.coordinates[0].get_y(), start);
+        game.tick();
+        assert_eq!(game.current_block.coordinates[0].get_y(), start + 1);
+    }
+
+    #[test]
+    fn test_twenty_g_and_are() {
+        let mut game = master(500);
+        assert!(game.gravity().is_instant());
+        game.apply(Action::HardDrop);
+
+        // Nothing is active until the ARE has passed
+        assert!(game.active_block().is_none());
+        assert!(!game.apply(Action::Left));
+        for _ in 0..game.spawn_delay() {
+            game.tick();
+        }
+
+        // The new block appears already resting on the stack
+        assert!(game.active_block().is_some());
+        assert!(game.grounded());
+    }
+
+    #[test]
+    fn test_lock_delay() {
+        let mut game = master(500);
+        game.fall(u32::MAX);
+        let delay = game.lock_delay().unwrap();
+        for _ in 0..delay - 1 {
+            game.tick();
+        }
+        assert_eq!(game.pieces, 0);
+        game.tick();
+        assert_eq!(game.pieces, 1);
+
+        // Soft drop on the ground locks straight away
+        let mut game = master(500);
+        game.fall(u32::MAX);
+        assert!(game.apply(Action::SoftDrop));
+        assert_eq!(game.pieces, 1);
+    }
+
+    #[test]
+    fn test_master_levels_and_grade() {
+        let mut game = master(98);
+        game.advance_master_level(0);
+        assert_eq!(game.level, 99);
+        // A block alone can't finish the section
+        game.advance_master_level(0);
+        assert_eq!(game.level, 99);
+        game.frame = 600;
+        game.advance_master_level(1);
+        assert_eq!(game.level, 100);
+        assert_eq!(game.splits, vec![600]);
+
+        // Passing level 300 in time with enough points keeps GM possible
+        game.level = 299;
+        game.score = 200000;
+        game.advance_master_level(1);
+        assert!(game.gm_eligible);
+
+        // Passing it short of points rules GM out
+        let mut game = master(299);
+        game.advance_master_level(2);
+        assert!(!game.gm_eligible);
+
+        game.level = 998;
+        game.score = 200000;
+        game.advance_master_level(1);
+        assert!(game.finished);
+        assert_eq!(game.grade().to_string(), "S9");
+    }
+
+    #[test]
+    fn test_action_names() {
+        assert_eq!(Action::SoftDrop.to_string(), "SoftDrop");
+        assert_eq!("HardDrop".parse(), Ok(Action::HardDrop));
+        assert_eq!("redo".parse(), Ok(Action::Redo));
+        assert!("Spin".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = GameConfig {
+            mode: GameMode::Ultra,
+            time_limit: 180,
+            ..config(99)
+        };
+        assert_eq!(config.to_string().parse::<GameConfig>(), Ok(config));
+        assert!("mode=ultra colour=red".parse::<GameConfig>().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_config() {
+        assert!(config(0).validate().is_ok());
+        assert!(GameConfig {
+            width: 2,
+            ..config(0)
+        }
+        .validate()
+        .is_err());
+        assert!(GameConfig {
+            width: usize::MAX,
+            ..config(0)
+        }
+        .validate()
+        .is_err());
+        assert!(GameConfig {
+            level: 0,
+            ..config(0)
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_garbage_is_only_checked_in_dig_mode() {
+        // The default garbage height doesn't fit a 10-row board
+        let short = GameConfig {
+            height: 10,
+            ..config(0)
+        };
+        assert_eq!(short.validate(), Ok(()));
+        assert_eq!(
+            GameConfig {
+                mode: GameMode::Dig,
+                ..short.clone()
+            }
+            .validate(),
+            Err("garbage height must be between 1 and 7, got 10".to_string())
+        );
+        assert!(GameConfig {
+            mode: GameMode::Dig,
+            garbage_lines: 0,
+            ..config(0)
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_marathon_lines_are_only_checked_in_marathon_mode() {
+        let no_goal = GameConfig {
+            marathon_lines: 0,
+            ..config(0)
+        };
+        assert_eq!(no_goal.validate(), Ok(()));
+        assert_eq!(
+            GameConfig {
+                mode: GameMode::Marathon,
+                ..no_goal
+            }
+            .validate(),
+            Err("marathon needs at least one line".to_string())
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use crate::block::BlockShape;
+use crate::board::Board;
+use crate::game::{Action, Game};
+use crate::replay::ReplayEvent;
+use crate::scoring::Clear;
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+
+/// The game as a piece appears: everything placing it changes, and its
+/// shape. The random generator is kept too, so redone and replayed games draw
+/// the same pieces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    board: Board,
+    shape: BlockShape,
+    queue: VecDeque<BlockShape>,
+    hold: Option<BlockShape>,
+    score: i32,
+    lines: i32,
+    level: u32,
+    pieces: u32,
+    combo: i32,
+    best_combo: i32,
+    back_to_back: bool,
+    last_clear: Option<Clear>,
+    finesse_faults: u32,
+    rng: ChaCha8Rng,
+    game_over: bool,
+    finished: bool,
+}
+
+/// Games with [`GameConfig::undo`] set outside the competitive modes keep a
+/// snapshot per locked piece, so placements can be undone and redone without
+/// limit. Undo and redo are actions like any other and are recorded in
+/// replays.
+///
+/// [`GameConfig::undo`]: crate::game::GameConfig::undo
+impl Game {
+    /// Whether placements can be undone in this game.
+    pub fn keeps_history(&self) -> bool {
+        self.config.undo && !self.config.mode.is_competitive()
+    }
+
+    /// Placements that can be undone.
+    pub fn undo_depth(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Undone placements that can be redone.
+    pub fn redo_depth(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Notes the state as a new piece appears, to go back to if it is
+    /// placed and then undone.
+    pub(crate) fn remember_spawn(&mut self) {
+        if self.keeps_history() {
+            self.piece_start = Some(self.snapshot());
+        }
+    }
+
+    /// Records the current piece's placement. A new placement replaces
+    /// anything that was undone.
+    pub(crate) fn remember_placement(&mut self) {
+        if let Some(snapshot) = self.piece_start.take() {
+            self.undo_stack.push(snapshot);
+            self.redo_stack.clear();
+        }
+    }
+
+    /// Applies [`Action::Undo`] or [`Action::Redo`]. These work after topping
+    /// out too. Returns whether there was anything to undo or redo.
+    pub(crate) fn step_history(&mut self, action: Action) -> bool {
+        let popped = match action {
+            Action::Undo => self.undo_stack.pop(),
+            _ => self.redo_stack.pop(),
+        };
+        let Some(snapshot) = popped else {
+            return false;
+        };
+        let current = self.piece_start.take().unwrap_or_else(|| self.snapshot());
+        match action {
+            Action::Undo => self.redo_stack.push(current),
+            _ => self.undo_stack.push(current),
+        }
+        self.restore(snapshot);
+        self.events.push(ReplayEvent {
+            frame: self.frame,
+            action,
+        });
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            shape: self.current_block.shape,
+            queue: self.queue.clone(),
+            hold: self.hold,
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+            pieces: self.pieces,
+            combo: self.combo,
+            best_combo: self.best_combo,
+            back_to_back: self.back_to_back,
+            last_clear: self.last_clear,
+            finesse_faults: self.finesse_faults,
+            rng: self.rng.clone(),
+            game_over: self.game_over,
+            finished: self.finished,
+        }
+    }
+
+    /// Goes back to a snapshot, with its shape spawning afresh.
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.piece_start = Some(snapshot.clone());
+        self.board = snapshot.board;
+        self.queue = snapshot.queue;
+        self.hold = snapshot.hold;
+        self.score = snapshot.score;
+        self.lines = snapshot.lines;
+        self.level = snapshot.level;
+        self.pieces = snapshot.pieces;
+        self.combo = snapshot.combo;
+        self.best_combo = snapshot.best_combo;
+        self.back_to_back = snapshot.back_to_back;
+        self.last_clear = snapshot.last_clear;
+        self.finesse_faults = snapshot.finesse_faults;
+        self.finesse = None;
+        self.rng = snapshot.rng;
+        self.are = 0;
+        self.game_over = false;
+        self.spawn(snapshot.shape);
+        self.game_over |= snapshot.game_over;
+        self.finished = snapshot.finished;
+        self.can_hold = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameConfig, GameMode};
+    use crate::replay::Playback;
+
+    fn endless() -> Game {
+        Game::new(GameConfig {
+            seed: 8,
+            undo: true,
+            ..GameConfig::default()
+        })
+    }
+
+    fn drop_pieces(game: &mut Game, count: usize) {
+        for i in 0..count {
+            let side = if i % 2 == 0 {
+                Action::Left
+            } else {
+                Action::Right
+            };
+            for _ in 0..i % 4 {
+                game.apply(side);
+            }
+            game.apply(Action::HardDrop);
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut game = endless();
+        let first = game.current_block.clone();
+        let queue = game.queue.clone();
+        drop_pieces(&mut game, 2);
+        let (board, score, block, after_queue) = (
+            game.board.clone(),
+            game.score,
+            game.current_block.clone(),
+            game.queue.clone(),
+        );
+        assert_eq!(game.undo_depth(), 2);
+
+        assert!(game.apply(Action::Undo));
+        assert!(game.apply(Action::Undo));
+        assert!(!game.apply(Action::Undo));
+        assert!(game.board.cells.is_empty());
+        assert_eq!(game.score, 0);
+        assert_eq!(game.current_block, first);
+        assert_eq!(game.queue, queue);
+
+        assert!(game.apply(Action::Redo));
+        assert!(game.apply(Action::Redo));
+        assert!(!game.apply(Action::Redo));
+        assert_eq!(game.board, board);
+        assert_eq!(game.score, score);
+        assert_eq!(game.current_block, block);
+        assert_eq!(game.queue, after_queue);
+    }
+
+    #[test]
+    fn test_new_placement_drops_redo() {
+        let mut game = endless();
+        drop_pieces(&mut game, 3);
+        game.apply(Action::Undo);
+        game.apply(Action::Undo);
+        assert_eq!(game.redo_depth(), 2);
+
+        game.apply(Action::HardDrop);
+        assert_eq!(game.redo_depth(), 0);
+        assert_eq!(game.undo_depth(), 2);
+    }
+
+    #[test]
+    fn test_undo_after_topping_out() {
+        let mut game = endless();
+        while !game.ended() {
+            game.apply(Action::HardDrop);
+        }
+        assert!(game.apply(Action::Undo));
+        assert!(!game.ended());
+        assert!(game.apply(Action::Left));
+    }
+
+    #[test]
+    fn test_competitive_modes_have_no_history() {
+        let mut game = Game::new(GameConfig {
+            mode: GameMode::Sprint,
+            undo: true,
+            ..GameConfig::default()
+        });
+        game.apply(Action::HardDrop);
+        assert!(!game.keeps_history());
+        assert!(!game.apply(Action::Undo));
+    }
+
+    #[test]
+    fn test_replay_with_undo() {
+        let mut game = endless();
+        drop_pieces(&mut game, 4);
+        game.apply(Action::Undo);
+        game.apply(Action::Undo);
+        drop_pieces(&mut game, 3);
+        game.apply(Action::Undo);
+        game.apply(Action::Redo);
+        for _ in 0..100 {
+            game.tick();
+        }
+
+        let mut playback = Playback::new(game.replay());
+        while !playback.finished() {
+            playback.step();
+        }
+        assert_eq!(playback.game.board, game.board);
+        assert_eq!(playback.game.score, game.score);
+        assert_eq!(playback.game.queue, game.queue);
+    }
+
+    #[test]
+    fn test_replay_with_undo_after_topping_out() {
+        // Tops out on a tick rather than an input, so the undo comes on a
+        // later step of the playback
+        let mut game = endless();
+        while !game.ended() {
+            game.apply(Action::SoftDrop);
+            game.tick();
+        }
+        for _ in 0..60 {
+            game.tick();
+        }
+        for _ in 0..6 {
+            game.apply(Action::Undo);
+        }
+        drop_pieces(&mut game, 2);
+        for _ in 0..50 {
+            game.tick();
+        }
+        assert!(!game.ended());
+
+        let mut playback = Playback::new(game.replay());
+        while !playback.finished() {
+            playback.step();
+        }
+        assert_eq!(playback.game.frame, game.frame);
+        assert_eq!(playback.game.board, game.board);
+        assert_eq!(playback.game.pieces, game.pieces);
+        assert_eq!(playback.game.queue, game.queue);
+    }
+
+    #[test]
+    fn test_history_is_opt_in() {
+        let mut game = Game::new(GameConfig {
+            seed: 8,
+            ..GameConfig::default()
+        });
+        drop_pieces(&mut game, 3);
+        assert!(!game.keeps_history());
+        assert_eq!(game.undo_depth(), 0);
+        assert!(!game.apply(Action::Undo));
+    }
+}
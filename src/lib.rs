@@ -0,0 +1,66 @@
+//! A Tetris engine with a terminal frontend, bots and training tools.
+//!
+//! The core types are re-exported at the root: a [`Board`] of locked cells,
+//! the falling [`Block`] of a [`BlockShape`] made of [`Point`]s, and the
+//! [`Game`] that runs them frame by frame under a [`GameConfig`]. Boards use
+//! screen coordinates: x grows to the right and y downward, from the top row.
+//!
+//! ```
+//! use tetris::{Action, Game, GameConfig};
+//!
+//! let mut game = Game::new(GameConfig {
+//!     seed: 7,
+//!     ..GameConfig::default()
+//! });
+//! game.apply(Action::HardDrop);
+//! game.tick();
+//! assert_eq!(game.pieces, 1);
+//! ```
+//!
+//! The rest of the engine lives in its modules:
+//!
+//! - [`scoring`], [`finesse`] and [`history`]: what a lock scores, how many
+//!   presses it took, and undo.
+//! - [`puzzle`], [`opener`], [`practice`] and [`fumen`]: set-up boards and
+//!   piece sequences, and the fumen format they can be shared in.
+//! - [`replay`]: recorded games, and `save`, with the `serde` feature:
+//!   suspended games.
+//! - `schema`, with the `serde` feature: serde support and versioned JSON
+//!   documents for the types above, which save and replay files are.
+//! - [`search`], [`bot`], [`simulate`] and [`tune`]: the placement search, the
+//!   bot built on it, and batches of bot games to measure and tune it.
+//! - `tbp`, with the `tbp` feature (on by default): the Tetris Bot Protocol, to
+//!   play external bots or serve this one.
+//! - [`env`](mod@env): a Gym-style environment for reinforcement learning, also
+//!   available from Python with the `python` feature.
+
+pub mod block;
+pub mod board;
+pub mod bot;
+pub mod env;
+pub mod finesse;
+pub mod fumen;
+pub mod game;
+pub mod history;
+pub mod opener;
+pub mod point;
+pub mod practice;
+pub mod puzzle;
+#[cfg(feature = "python")]
+mod python;
+pub mod replay;
+#[cfg(feature = "serde")]
+pub mod save;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod scoring;
+pub mod search;
+pub mod simulate;
+#[cfg(feature = "tbp")]
+pub mod tbp;
+pub mod tune;
+
+pub use block::{Block, BlockShape};
+pub use board::{Board, Cell};
+pub use game::{Action, Game, GameConfig, GameMode};
+pub use point::Point;
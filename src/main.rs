@@ -1,174 +1,155 @@
-mod block;
-mod board;
-mod point;
-
-use crate::block::{Block, BlockShape};
-use crate::board::Board;
-use crate::point::Point;
-use crossterm::event::{Event, KeyCode};
-use crossterm::{event, execute, terminal};
-use std::io::{self};
-use std::time::{Duration, Instant};
-use tui::backend::CrosstermBackend;
-use tui::layout::{Constraint, Direction, Layout};
-use tui::widgets::{Block as TuiBlock, Borders, Paragraph};
-use tui::Terminal;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup terminal
-    terminal::enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Initialize game state
-    let mut board = Board::new(10, 20); // 10x20 board
-    let mut current_block = Block::new(Point::new(4, 0), BlockShape::get_rand()); // Start with a Line block
-    let mut game_over = false;
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(500);
-    let mut score = 0; // Initialize the score
-
-    loop {
-        // Draw the game state
-        terminal.draw(|f| {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Percentage(80), // Board
-                    Constraint::Percentage(20), // Score
-                ])
-                .split(f.size());
-
-            // Draw the board
-            let board_widget = draw_board(&board, &current_block);
-            f.render_widget(board_widget, chunks[0]);
-
-            // Draw the score
-            let score_widget = Paragraph::new(format!("Score: {}", score))
-                .block(TuiBlock::default().borders(Borders::ALL).title("Score"));
-            f.render_widget(score_widget, chunks[1]);
-        })?;
-
-        // Handle user input
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Left => {
-                        if let Some(moved_block) =
-                            current_block.translate(-1, 0, board.x_dim, board.y_dim)
-                        {
-                            if !board.block_touches(&moved_block) {
-                                current_block = moved_block;
-                            }
-                        }
-                    }
-                    KeyCode::Right => {
-                        if let Some(moved_block) =
-                            current_block.translate(1, 0, board.x_dim, board.y_dim)
-                        {
-                            if !board.block_touches(&moved_block) {
-                                current_block = moved_block;
-                            }
-                        }
-                    }
-                    KeyCode::Down => {
-                        if let Some(moved_block) =
-                            current_block.translate(0, 1, board.x_dim, board.y_dim)
-                        {
-                            if !board.block_touches(&moved_block) {
-                                current_block = moved_block;
-                            }
-                        }
-                    }
-                    KeyCode::Char('r') | KeyCode::Up => {
-                        if let Ok(rotated_block) = current_block.rotate() {
-                            if !board.block_touches(&rotated_block) {
-                                current_block = rotated_block;
-                            }
-                        }
-                    }
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        // Auto-drop block
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
-            if let Some(moved_block) = current_block.translate(0, 1, board.x_dim, board.y_dim) {
-                if board.block_touches(&moved_block) {
-                    board.place_block(&current_block);
-                    score += board.clear_board(); // Update score based on cleared rows
-                    current_block = Block::new(Point::new(4, 0), BlockShape::get_rand());
-                    if board.block_touches(&current_block) {
-                        game_over = true;
-                    }
-                } else {
-                    current_block = moved_block;
-                }
-            } else {
-                board.place_block(&current_block);
-                score += board.clear_board(); // Update score based on cleared rows
-                current_block = Block::new(Point::new(4, 0), BlockShape::get_rand());
-                if board.block_touches(&current_block) {
-                    game_over = true;
-                }
-            }
-        }
-
-        if game_over {
-            terminal.draw(|f| {
-                let size = f.size();
-                let game_over_widget = Paragraph::new("Game Over! Press 'Q' to quit.")
-                    .block(TuiBlock::default().borders(Borders::ALL).title("Game Over"));
-                f.render_widget(game_over_widget, size);
-            })?;
-            loop {
-                if let Event::Key(key) = event::read()? {
-                    if key.code == KeyCode::Char('q') || key.code == KeyCode::Char('Q') {
-                        break;
-                    }
-                }
+mod cli;
+mod config;
+mod records;
+mod theme;
+mod ui;
+
+use crate::cli::{Cli, Command};
+use clap::Parser;
+use std::error::Error;
+#[cfg(feature = "tbp")]
+use std::io;
+use std::thread;
+use tetris::bot::{Bot, Player, Weights};
+use tetris::game::{GameConfig, GameMode};
+use tetris::opener::Opener;
+use tetris::puzzle::Puzzle;
+use tetris::replay::Replay;
+use tetris::simulate::Simulation;
+#[cfg(feature = "tbp")]
+use tetris::tbp::Client;
+use tetris::tune::Tuner;
+use tetris::Game;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let bot = match &cli.weights {
+        Some(path) => Bot {
+            weights: Weights::load(path)?,
+        },
+        None => Bot::default(),
+    };
+    #[cfg(feature = "tbp")]
+    if cli.tbp {
+        return tetris::tbp::serve(&bot, io::stdin().lock(), io::stdout().lock());
+    }
+    let settings = cli.settings()?;
+
+    if let Some(Command::Simulate(args)) = &cli.command {
+        let simulation = Simulation {
+            config: settings.game.clone(),
+            games: args.games,
+            max_pieces: args.max_pieces,
+            threads: args.threads.unwrap_or_else(cores),
+        };
+        let outcomes = simulation.run(|| -> Result<Box<dyn Player>, Box<dyn Error>> {
+            #[cfg(feature = "tbp")]
+            if let Some(command) = &args.tbp_bot {
+                return Ok(Box::new(Client::spawn(command)?));
             }
-            break;
-        }
+            Ok(Box::new(bot))
+        })?;
+        print!(
+            "{}",
+            tetris::simulate::report(&outcomes, args.format, args.per_game)?
+        );
+        return Ok(());
+    }
+    if let Some(Command::Tune(args)) = &cli.command {
+        let tuner = Tuner {
+            config: settings.game.clone(),
+            population: args.population,
+            generations: args.generations,
+            games: args.games,
+            max_pieces: args.max_pieces,
+            mutation: args.mutation,
+            threads: args.threads.unwrap_or_else(cores),
+            seed: settings.game.seed,
+        };
+        let best = tuner.run(bot.weights, |generation, best| {
+            eprintln!("Generation {}: best {} lines", generation, best.fitness);
+        })?;
+        best.weights.save(&args.output)?;
+        println!(
+            "Best of {} games: {} lines, written to {}",
+            args.games,
+            best.fitness,
+            args.output.display()
+        );
+        print!("{}", best.weights);
+        return Ok(());
     }
 
-    // Restore terminal
-    terminal::disable_raw_mode()?;
-    execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
-    Ok(())
-}
-
-fn draw_board<'a>(board: &Board, current_block: &Block) -> Paragraph<'a> {
-    let mut grid = vec![vec!['.'; board.x_dim as usize]; board.y_dim as usize];
-
-    // Mark filled points
-    for point in &board.filled {
-        if point.get_x() >= 0 && point.get_y() >= 0 {
-            grid[point.get_y() as usize][point.get_x() as usize] = '#';
-        }
+    if let Some(path) = &cli.replay {
+        return ui::run_replay(&settings, Replay::load(path)?);
     }
 
-    // Mark current block
-    for point in &current_block.coordinates {
-        if point.get_x() >= 0 && point.get_y() >= 0 {
-            grid[point.get_y() as usize][point.get_x() as usize] = '*';
+    let opener = cli.opener.as_deref().map(Opener::load).transpose()?;
+    // Only someone at the keyboard undoes placements or cares about finesse
+    let config = GameConfig {
+        undo: !cli.headless,
+        finesse: !cli.headless,
+        ..settings.game.clone()
+    };
+    let mut game = match (&cli.resume, &cli.puzzle, &opener) {
+        (Some(path), _, _) => Game::load(path)?,
+        (_, Some(path), _) => Game::start(config, Some(Puzzle::load(path)?)),
+        (_, _, Some(opener)) => Game::new(opener.config(config)),
+        _ => Game::new(config),
+    };
+    if let Some(data) = &cli.fumen {
+        let pages = tetris::fumen::decode(data)?;
+        let page = cli
+            .fumen_page
+            .checked_sub(1)
+            .and_then(|i| pages.get(i))
+            .ok_or_else(|| format!("the fumen has {} pages", pages.len()))?;
+        game.load_fumen_page(page)?;
+    }
+    // Board edits don't go through actions, so they can't be replayed. The
+    // mode comes from the save when resuming, so check the game itself.
+    if cli.record.is_some() && game.config.mode == GameMode::Practice {
+        return Err("practice games can't be recorded".into());
+    }
+    let mut player: Option<Box<dyn Player>> = if cli.bot { Some(Box::new(bot)) } else { None };
+    #[cfg(feature = "tbp")]
+    if let Some(command) = &cli.tbp_bot {
+        player = Some(Box::new(Client::spawn(command)?));
+    }
+    if let (true, Some(player)) = (cli.headless, &mut player) {
+        tetris::bot::play(player.as_mut(), &mut game, cli.max_pieces)?;
+        let outcome = if game.ended() { "ended" } else { "still going" };
+        println!(
+            "{} game, seed {}: {}",
+            game.config.mode, game.config.seed, outcome
+        );
+        println!(
+            "Pieces: {}  Lines: {}  Score: {}",
+            game.pieces, game.lines, game.score
+        );
+        if let Some(path) = &cli.record {
+            game.replay().save(path)?;
         }
+        return Ok(());
     }
-
-    // Render the board into a string
-    let board_string = grid
-        .into_iter()
-        .map(|row| row.into_iter().collect::<String>())
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    Paragraph::new(board_string).block(TuiBlock::default().borders(Borders::ALL).title("Board"))
+    let game = ui::run(
+        game,
+        &settings,
+        cli.record.as_deref(),
+        cli.save.as_deref(),
+        opener.as_ref(),
+        player
+            .as_mut()
+            .map(|player| player.as_mut() as &mut dyn Player),
+        &bot,
+    )?;
+    if cli.export_fumen {
+        println!("{}", tetris::fumen::encode(&[game.fumen_page()?])?);
+    }
+    Ok(())
 }
 
+/// Threads to play on when not told: one per core.
+fn cores() -> usize {
+    thread::available_parallelism().map_or(1, |threads| threads.get())
+}
@@ -0,0 +1,404 @@
+use crate::block::{Block, BlockShape};
+use crate::board::{Board, Cell};
+use crate::game::{moved_block, Action, GameConfig, GameMode, Randomizer};
+use crate::point::Point;
+use crate::search::route_to;
+use arrayvec::ArrayVec;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+/// An opener: the board shapes to build from an empty board, one stage per
+/// bag of pieces, up to the setup before the first line clear.
+///
+/// Opener files are `key = value` lines followed by one `board:` section per
+/// stage, each the whole setup so far as rows of piece letters aligned to the
+/// bottom of the board (see [`Board::from_rows`]). Lines starting with `#`
+/// are comments:
+///
+/// ```text
+/// name = Two bags
+/// board:
+/// OO........
+/// OOIIII....
+/// board:
+/// OOLLL.....
+/// OOIIIIL...
+/// ```
+///
+/// `height` sets the board height (default 20). The pieces a stage adds must
+/// each be a whole piece of their letter; a piece left out, like the T held
+/// for a T-spin, simply doesn't appear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opener {
+    pub name: String,
+    pub stages: Vec<Board>,
+}
+
+#[derive(Debug)]
+pub struct OpenerError {
+    message: String,
+}
+
+impl fmt::Display for OpenerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid opener: {}", self.message)
+    }
+}
+
+impl std::error::Error for OpenerError {}
+
+fn invalid(message: String) -> OpenerError {
+    OpenerError { message }
+}
+
+impl Opener {
+    pub fn load(path: &Path) -> Result<Opener, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read opener {}: {}", path.display(), e))?;
+        Ok(text.parse()?)
+    }
+
+    /// `config` set up for training this opener: a practice game on a board
+    /// of the opener's size, with pieces dealt in bags.
+    pub fn config(&self, config: GameConfig) -> GameConfig {
+        let board = self.last_stage();
+        GameConfig {
+            mode: GameMode::Practice,
+            randomizer: Randomizer::Bag,
+            width: board.x_dim as usize,
+            height: board.y_dim as usize,
+            ..config
+        }
+    }
+
+    /// The first stage not yet built on `board`, or `None` once the whole
+    /// opener is.
+    pub fn stage(&self, board: &Board) -> Option<usize> {
+        self.stages
+            .iter()
+            .position(|stage| missing(stage, board).next().is_some())
+    }
+
+    /// Where the opener wants `start`'s piece next: a spot of its shape from
+    /// the earliest unbuilt stage that still needs one, if the piece can get
+    /// there and would stay put. `None` when the piece isn't needed now or
+    /// can't be placed yet, which usually means it should be held.
+    pub fn target(&self, board: &Board, start: &Block) -> Option<Block> {
+        let first = self.stage(board)?;
+        let cells = self.stages[first..].iter().find_map(|stage| {
+            let cells: Vec<Point> = missing(stage, board)
+                .filter(|point| stage.cell(point) == Some(Cell::Block(start.shape)))
+                .collect();
+            (!cells.is_empty()).then_some(cells)
+        })?;
+        pieces(start.shape, &cells)?.into_iter().find(|piece| {
+            !board.block_touches(piece)
+                && moved_block(board, piece, Action::SoftDrop).is_none()
+                // Openers are drilled in practice mode, which never has 20G
+                && route_to(board, start, piece, false).is_some()
+        })
+    }
+
+    /// Filled cells of `board` that don't belong to the opener, either
+    /// outside it or holding the wrong piece.
+    pub fn deviations(&self, board: &Board) -> Vec<Point> {
+        let last = self.last_stage();
+        board
+            .sorted_cells()
+            .into_iter()
+            .filter(|(point, cell)| last.cell(point) != Some(*cell))
+            .map(|(point, _)| point)
+            .collect()
+    }
+
+    fn last_stage(&self) -> &Board {
+        self.stages.last().expect("openers have at least one stage")
+    }
+}
+
+/// Cells of `stage` that `board` doesn't have with the same piece.
+fn missing<'a>(stage: &'a Board, board: &'a Board) -> impl Iterator<Item = Point> + 'a {
+    stage
+        .sorted_cells()
+        .into_iter()
+        .filter(|(point, cell)| board.cell(point) != Some(*cell))
+        .map(|(point, _)| point)
+}
+
+/// Splits `cells` into whole pieces of `shape`, or `None` if they can't be.
+fn pieces(shape: BlockShape, cells: &[Point]) -> Option<Vec<Block>> {
+    let Some(&first) = cells.iter().min_by_key(|p| (p.get_y(), p.get_x())) else {
+        return Some(Vec::new());
+    };
+    for orientation in orientations(shape) {
+        // Line the orientation's top-left cell up with the first cell
+        let anchor = orientation[0];
+        let block = Block {
+            shape,
+            coordinates: orientation
+                .iter()
+                .map(|p| {
+                    Point::new(
+                        p.get_x() - anchor.get_x() + first.get_x(),
+                        p.get_y() - anchor.get_y() + first.get_y(),
+                    )
+                })
+                .collect(),
+        };
+        if !block.coordinates.iter().all(|p| cells.contains(p)) {
+            continue;
+        }
+        let rest: Vec<Point> = cells
+            .iter()
+            .filter(|p| !block.coordinates.contains(p))
+            .copied()
+            .collect();
+        if let Some(mut others) = pieces(shape, &rest) {
+            others.insert(0, block);
+            return Some(others);
+        }
+    }
+    None
+}
+
+/// Each distinct rotation of `shape`, as cells sorted top to bottom and left
+/// to right.
+fn orientations(shape: BlockShape) -> Vec<ArrayVec<Point, 4>> {
+    let mut block = Block::new(Point::new(4, 4), shape);
+    let mut found = Vec::new();
+    for _ in 0..4 {
+        let mut cells = block.coordinates.clone();
+        cells.sort_by_key(|p| (p.get_y(), p.get_x()));
+        let origin = cells[0];
+        let cells: ArrayVec<Point, 4> = cells
+            .iter()
+            .map(|p| Point::new(p.get_x() - origin.get_x(), p.get_y() - origin.get_y()))
+            .collect();
+        if !found.contains(&cells) {
+            found.push(cells);
+        }
+        block = block.rotate().expect("rotating away from the edges");
+    }
+    found
+}
+
+impl FromStr for Opener {
+    type Err = OpenerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = String::new();
+        let mut height = 20;
+        let mut sections: Vec<Vec<&str>> = Vec::new();
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "board:" {
+                sections.push(Vec::new());
+                continue;
+            }
+            if let Some(rows) = sections.last_mut() {
+                rows.push(line);
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected 'key = value', got '{}'", line)))?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => name = value.to_string(),
+                "height" => {
+                    height = value
+                        .parse()
+                        .map_err(|_| invalid(format!("bad height '{}'", value)))?
+                }
+                key => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+
+        if sections.is_empty() {
+            return Err(invalid("no board".to_string()));
+        }
+        let width = sections[0].first().map_or(0, |row| row.chars().count());
+        if width < 4 || height < 4 {
+            return Err(invalid(format!(
+                "board must be at least 4x4, got {}x{}",
+                width, height
+            )));
+        }
+        let mut stages: Vec<Board> = Vec::new();
+        for rows in &sections {
+            let stage = Board::from_rows(width, height, rows).map_err(invalid)?;
+            check_stage(&stage, stages.last(), stages.len() + 1)?;
+            stages.push(stage);
+        }
+        Ok(Opener { name, stages })
+    }
+}
+
+/// Checks that stage `number` only has piece cells, keeps everything in the
+/// stage before it, and adds whole pieces without filling a row.
+fn check_stage(stage: &Board, previous: Option<&Board>, number: usize) -> Result<(), OpenerError> {
+    if stage.cells.values().any(|cell| *cell == Cell::Garbage) {
+        return Err(invalid(format!(
+            "stage {} has cells without a piece letter",
+            number
+        )));
+    }
+    if stage.rows().iter().any(|row| !row.contains('.')) {
+        return Err(invalid(format!("stage {} fills a whole row", number)));
+    }
+    let empty = Board::new(stage.x_dim as usize, stage.y_dim as usize);
+    let previous = previous.unwrap_or(&empty);
+    if missing(previous, stage).next().is_some() {
+        return Err(invalid(format!(
+            "stage {} leaves out cells of the stage before",
+            number
+        )));
+    }
+    let added: Vec<Point> = missing(stage, previous).collect();
+    for shape in BlockShape::iter() {
+        let cells: Vec<Point> = added
+            .iter()
+            .filter(|point| stage.cell(point) == Some(Cell::Block(shape)))
+            .copied()
+            .collect();
+        if pieces(shape, &cells).is_none() {
+            return Err(invalid(format!(
+                "the {} cells of stage {} are not whole pieces",
+                shape.letter(),
+                number
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Writes the opener file form, each stage from its top filled row down.
+impl fmt::Display for Opener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name = {}", self.name)?;
+        writeln!(f, "height = {}", self.last_stage().y_dim)?;
+        for stage in &self.stages {
+            writeln!(f, "board:")?;
+            for row in stage
+                .rows()
+                .iter()
+                .skip_while(|row| !row.contains(|c| c != '.'))
+            {
+                writeln!(f, "{}", row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    const TSD: &str = include_str!("../openers/tsd.opener");
+    const PCO: &str = include_str!("../openers/pco.opener");
+    const TKI: &str = include_str!("../openers/tki.opener");
+    const DT: &str = include_str!("../openers/dt_cannon.opener");
+
+    /// Whether the pieces a stage adds can be placed, one at a time from the
+    /// spawn point, in some order.
+    fn buildable(opener: &Opener, board: &Board) -> bool {
+        if opener.stage(board).is_none() {
+            return true;
+        }
+        let spawn = Point::new(board.x_dim / 2 - 1, 0);
+        BlockShape::iter().any(|shape| {
+            opener
+                .target(board, &Block::new(spawn, shape))
+                .is_some_and(|target| {
+                    let mut next = board.clone();
+                    next.place_block(&target);
+                    buildable(opener, &next)
+                })
+        })
+    }
+
+    #[test]
+    fn test_openers_are_buildable() {
+        for text in [TSD, PCO, TKI, DT] {
+            let opener: Opener = text.parse().unwrap();
+            let board = Board::new(10, 20);
+            assert!(buildable(&opener, &board), "{}", opener.name);
+            assert_eq!(opener.to_string().parse::<Opener>().unwrap(), opener);
+        }
+    }
+
+    #[test]
+    fn test_stages_and_targets() {
+        let opener: Opener = PCO.parse().unwrap();
+        assert_eq!(opener.stages.len(), 2);
+        let board = Board::new(10, 20);
+        assert_eq!(opener.stage(&board), Some(0));
+
+        // The T is kept for the second stage, which needs the first built
+        let t = Block::new(Point::new(4, 0), BlockShape::T);
+        assert_eq!(opener.target(&board, &t), None);
+        let mut built = opener.stages[0].clone();
+        assert_eq!(opener.stage(&built), Some(1));
+        assert!(opener.target(&built, &t).is_some());
+
+        built = opener.stages[1].clone();
+        assert_eq!(opener.stage(&built), None);
+        assert!(opener.deviations(&built).is_empty());
+    }
+
+    #[test]
+    fn test_deviations() {
+        let opener: Opener = TSD.parse().unwrap();
+        let mut board = Board::new(10, 20);
+        board.place_block(&Block::new(Point::new(0, 19), BlockShape::Line));
+        assert!(opener.deviations(&board).is_empty());
+
+        // The I belongs flat in the bottom left corner, not on top of itself
+        board.place_block(&Block::new(Point::new(3, 18), BlockShape::Line));
+        let wrong = opener.deviations(&board);
+        assert_eq!(wrong.len(), 4);
+        assert!(wrong.contains(&Point::new(6, 18)));
+    }
+
+    #[test]
+    fn test_training_config() {
+        let opener: Opener = PCO.parse().unwrap();
+        let game = Game::new(opener.config(GameConfig::default()));
+        assert_eq!(game.config.mode, GameMode::Practice);
+        assert_eq!(game.config.randomizer, Randomizer::Bag);
+
+        // Every bag of seven holds each shape once
+        let mut shapes: Vec<BlockShape> = game.queue.iter().take(6).copied().collect();
+        shapes.push(game.current_block.shape);
+        for shape in BlockShape::iter() {
+            assert!(shapes.contains(&shape));
+        }
+    }
+
+    #[test]
+    fn test_bad_openers() {
+        let bad = [
+            "name = x",
+            "board:\nOO..\nOO..\nboard:\nO...\nO...",
+            "board:\nTTT.\n.T#.",
+            "board:\nTTT.\n..T.",
+            "board:\nIIII",
+            "board:\nLLL.\nOOL.",
+            "size = 3\nboard:\nOO..\nOO..",
+        ];
+        for text in bad {
+            assert!(text.parse::<Opener>().is_err(), "{}", text);
+        }
+        let opener: Opener = "height = 6\nboard:\n..LL\nOO.L\nOO.L".parse().unwrap();
+        assert_eq!(opener.stages[0].y_dim, 6);
+    }
+}
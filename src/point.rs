@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// A cell position: x counts columns from the left, y rows from the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     x: i32,
     y: i32,
@@ -14,12 +16,14 @@ impl Point {
     pub fn get_y(&self) -> i32 {
         self.y
     }
+    /// The sum of both points, for offsetting one by the other.
     pub fn add(&self, other: &Point) -> Point {
         Point {
             x: self.get_x() + other.get_x(),
             y: self.get_y() + other.get_y(),
         }
     }
+    /// Whether the point lies right of the left edge and below the top edge.
     pub fn not_negative(&self) -> bool {
         self.get_x() >= 0 && self.get_y() >= 0
     }
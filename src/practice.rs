@@ -0,0 +1,94 @@
+use crate::block::BlockShape;
+use crate::board::Cell;
+use crate::game::{Game, PREVIEW_LENGTH};
+use crate::point::Point;
+
+/// Practice mode is a sandbox for drilling setups: cells can be painted onto
+/// the board, the next pieces chosen and gravity turned off. None of this
+/// goes through [`Game::apply`], so practice games can't be replayed.
+impl Game {
+    /// Fills an empty cell with garbage, or empties a filled one. Cells under
+    /// the falling block are left alone. Returns whether the cell changed.
+    pub fn toggle_cell(&mut self, point: Point) -> bool {
+        let inside = point.not_negative()
+            && point.get_x() < self.board.x_dim
+            && point.get_y() < self.board.y_dim;
+        if !inside || self.current_block.coordinates.contains(&point) {
+            return false;
+        }
+        if self.board.cells.remove(&point).is_none() {
+            self.board.cells.insert(point, Cell::Garbage);
+        }
+        true
+    }
+
+    /// Makes `shapes` the next pieces, followed by random ones.
+    pub fn set_next(&mut self, shapes: &[BlockShape]) {
+        self.queue = shapes.iter().copied().collect();
+        self.fill_queue(PREVIEW_LENGTH);
+    }
+
+    /// Turns gravity off or back on. Without gravity the block only moves
+    /// down when dropped.
+    pub fn toggle_gravity(&mut self) {
+        self.gravity_enabled = !self.gravity_enabled;
+        self.gravity_counter = 0;
+        self.lock_counter = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, GameConfig, GameMode};
+
+    fn practice() -> Game {
+        Game::new(GameConfig {
+            mode: GameMode::Practice,
+            seed: 8,
+            ..GameConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_paint_cells() {
+        let mut game = practice();
+        let spawned = game.current_block.coordinates[0];
+        assert!(!game.toggle_cell(spawned));
+        assert!(!game.toggle_cell(Point::new(10, 19)));
+
+        assert!(game.toggle_cell(Point::new(0, 19)));
+        assert!(game.toggle_cell(Point::new(1, 19)));
+        assert_eq!(game.board.rows()[19], "##........");
+        assert!(game.toggle_cell(Point::new(0, 19)));
+        assert_eq!(game.board.rows()[19], ".#........");
+    }
+
+    #[test]
+    fn test_choose_next_pieces() {
+        let mut game = practice();
+        game.set_next(&[BlockShape::T, BlockShape::Line]);
+        assert_eq!(game.queue.len(), PREVIEW_LENGTH);
+        game.apply(Action::HardDrop);
+        assert_eq!(game.current_block.shape, BlockShape::T);
+        game.apply(Action::HardDrop);
+        assert_eq!(game.current_block.shape, BlockShape::Line);
+    }
+
+    #[test]
+    fn test_gravity_off() {
+        let mut game = practice();
+        game.toggle_gravity();
+        let start = game.current_block.clone();
+        for _ in 0..1000 {
+            game.tick();
+        }
+        assert_eq!(game.current_block, start);
+
+        game.toggle_gravity();
+        for _ in 0..1000 {
+            game.tick();
+        }
+        assert!(game.pieces > 0);
+    }
+}
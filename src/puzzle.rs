@@ -0,0 +1,305 @@
+use crate::block::BlockShape;
+use crate::board::Board;
+use crate::game::Game;
+use crate::scoring::Spin;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What the player has to do to solve a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// Leave the board empty after a line clear.
+    ClearAll,
+    /// Clear this many lines with a single T-spin.
+    TSpin(i32),
+    /// Clear this many lines in total.
+    Lines(i32),
+}
+
+impl Goal {
+    /// Whether the goal has been reached by a lock that cleared `cleared`
+    /// lines with `spin`.
+    pub fn met(&self, game: &Game, cleared: i32, spin: Spin) -> bool {
+        match *self {
+            Goal::ClearAll => cleared > 0 && game.board.cells.is_empty(),
+            Goal::TSpin(lines) => spin == Spin::Full && cleared == lines,
+            Goal::Lines(lines) => game.lines >= lines,
+        }
+    }
+
+    /// A short instruction for the player.
+    pub fn description(&self) -> String {
+        match *self {
+            Goal::ClearAll => "Clear the whole board".to_string(),
+            Goal::TSpin(lines) => format!("T-spin {}", tspin_name(lines)),
+            Goal::Lines(1) => "Clear a line".to_string(),
+            Goal::Lines(lines) => format!("Clear {} lines", lines),
+        }
+    }
+}
+
+fn tspin_name(lines: i32) -> &'static str {
+    match lines {
+        1 => "single",
+        2 => "double",
+        _ => "triple",
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Goal::ClearAll => write!(f, "clear-all"),
+            Goal::TSpin(lines) => write!(f, "tspin-{}", tspin_name(lines)),
+            Goal::Lines(lines) => write!(f, "lines {}", lines),
+        }
+    }
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["clear-all"] => Ok(Goal::ClearAll),
+            ["tspin-single"] => Ok(Goal::TSpin(1)),
+            ["tspin-double"] => Ok(Goal::TSpin(2)),
+            ["tspin-triple"] => Ok(Goal::TSpin(3)),
+            ["lines", lines] => match lines.parse() {
+                Ok(lines) if lines > 0 => Ok(Goal::Lines(lines)),
+                _ => Err(format!("bad line count '{}'", lines)),
+            },
+            _ => Err(format!("unknown goal '{}'", s)),
+        }
+    }
+}
+
+/// A starting board, a fixed piece sequence and a goal to reach with them.
+///
+/// Puzzle files are `key = value` lines followed by the board as rows of
+/// cells (see [`Board::from_rows`]), aligned to the bottom of the board:
+///
+/// ```text
+/// # Fill the gap with two O pieces
+/// name = Two squares
+/// goal = clear-all
+/// pieces = O O
+/// board:
+/// ###....###
+/// ###....###
+/// ```
+///
+/// `limit` caps the number of pieces that may be placed (by default the whole
+/// sequence) and `height` sets the board height (default 20).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: Goal,
+    /// Shapes handed to the player, in order. The puzzle is failed when they
+    /// run out.
+    pub pieces: Vec<BlockShape>,
+    /// Most pieces that may be placed before the goal is reached.
+    pub limit: u32,
+    pub board: Board,
+}
+
+#[derive(Debug)]
+pub struct PuzzleError {
+    message: String,
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid puzzle: {}", self.message)
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+fn invalid(message: String) -> PuzzleError {
+    PuzzleError { message }
+}
+
+impl Puzzle {
+    pub fn load(path: &Path) -> Result<Puzzle, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read puzzle {}: {}", path.display(), e))?;
+        Ok(text.parse()?)
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = PuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = String::new();
+        let mut goal = None;
+        let mut pieces = Vec::new();
+        let mut limit = None;
+        let mut height = 20;
+        let mut rows = Vec::new();
+        let mut in_board = false;
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || (line.starts_with('#') && !in_board) {
+                continue;
+            }
+            if in_board {
+                rows.push(line);
+                continue;
+            }
+            if line == "board:" {
+                in_board = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected 'key = value', got '{}'", line)))?;
+            let value = value.trim();
+            let number = |value: &str| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| invalid(format!("bad {} '{}'", key.trim(), value)))
+            };
+            match key.trim() {
+                "name" => name = value.to_string(),
+                "goal" => goal = Some(value.parse().map_err(invalid)?),
+                "pieces" => {
+                    pieces = value
+                        .chars()
+                        .filter(|c| !c.is_whitespace())
+                        .map(|c| {
+                            BlockShape::from_letter(c)
+                                .ok_or_else(|| invalid(format!("unknown piece '{}'", c)))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "limit" => limit = Some(number(value)?),
+                "height" => height = number(value)? as usize,
+                key => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+
+        let goal = goal.ok_or_else(|| invalid("missing goal".to_string()))?;
+        if pieces.is_empty() {
+            return Err(invalid("no pieces".to_string()));
+        }
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width < 4 || height < 4 {
+            return Err(invalid(format!(
+                "board must be at least 4x4, got {}x{}",
+                width, height
+            )));
+        }
+        let board = Board::from_rows(width, height, &rows).map_err(invalid)?;
+        Ok(Puzzle {
+            name,
+            goal,
+            limit: limit.unwrap_or(pieces.len() as u32),
+            pieces,
+            board,
+        })
+    }
+}
+
+/// Writes the puzzle file form, with every row of the board.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pieces: String = self.pieces.iter().map(BlockShape::letter).collect();
+        writeln!(f, "name = {}", self.name)?;
+        writeln!(f, "goal = {}", self.goal)?;
+        writeln!(f, "pieces = {}", pieces)?;
+        writeln!(f, "limit = {}", self.limit)?;
+        writeln!(f, "height = {}", self.board.y_dim)?;
+        writeln!(f, "board:")?;
+        for row in self.board.rows() {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, GameConfig};
+
+    const TWO_SQUARES: &str = include_str!("../puzzles/two-squares.puzzle");
+
+    fn start(puzzle: &str) -> Game {
+        Game::start(GameConfig::default(), Some(puzzle.parse().unwrap()))
+    }
+
+    #[test]
+    fn test_puzzle_round_trip() {
+        let puzzle: Puzzle = TWO_SQUARES.parse().unwrap();
+        assert_eq!(puzzle.goal, Goal::ClearAll);
+        assert_eq!(puzzle.pieces, [BlockShape::Square, BlockShape::Square]);
+        assert_eq!(puzzle.limit, 2);
+        assert_eq!(puzzle.board.cells.len(), 12);
+
+        assert_eq!(puzzle.to_string().parse::<Puzzle>().unwrap(), puzzle);
+    }
+
+    #[test]
+    fn test_goals_parse() {
+        for goal in [Goal::ClearAll, Goal::TSpin(3), Goal::Lines(4)] {
+            assert_eq!(goal.to_string().parse(), Ok(goal));
+        }
+        assert!("lines 0".parse::<Goal>().is_err());
+        assert!("win".parse::<Goal>().is_err());
+    }
+
+    #[test]
+    fn test_bad_puzzles() {
+        assert!("pieces = T\nboard:\n....".parse::<Puzzle>().is_err());
+        assert!("goal = clear-all\nboard:\n....".parse::<Puzzle>().is_err());
+        assert!("goal = clear-all\npieces = Q\nboard:\n...."
+            .parse::<Puzzle>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_solve_two_squares() {
+        let mut game = start(TWO_SQUARES);
+        assert_eq!(game.current_block.shape, BlockShape::Square);
+        // Spawned at columns 4-5; the gap is columns 3-6
+        game.apply(Action::Left);
+        game.apply(Action::HardDrop);
+        assert!(!game.ended());
+        game.apply(Action::Right);
+        game.apply(Action::HardDrop);
+
+        assert!(game.finished);
+        assert!(game.board.cells.is_empty());
+    }
+
+    #[test]
+    fn test_solve_tspin_double() {
+        let mut game = start(include_str!("../puzzles/tspin-double.puzzle"));
+        // Stand the T up with its nub to the right and drop it beside the overhang
+        game.apply(Action::Rotate);
+        game.apply(Action::Left);
+        while game.apply(Action::SoftDrop) {}
+        // Turning it points it down into the slot under the overhang
+        assert!(game.apply(Action::Rotate));
+        game.apply(Action::HardDrop);
+
+        assert!(game.finished);
+        assert_eq!(game.lines, 2);
+    }
+
+    #[test]
+    fn test_fail_when_out_of_pieces() {
+        let mut game = start(TWO_SQUARES);
+        game.apply(Action::HardDrop);
+        game.apply(Action::HardDrop);
+
+        assert!(game.game_over);
+        assert!(!game.finished);
+    }
+}
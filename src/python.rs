@@ -0,0 +1,414 @@
+//! Python bindings, built with the `python` feature: the engine, boards,
+//! blocks and the training environment as the `tetris` extension module.
+//! Observations come as numpy arrays so agents can feed them to a network
+//! as they are.
+
+use crate::block::{Block, BlockShape};
+use crate::board::Board;
+use crate::env::{ActionSpace, EnvAction, Info, Observation, Rewards};
+use crate::game::{Action, Game, GameConfig};
+use crate::point::Point;
+use numpy::ndarray::{Array1, Array2, Array3};
+use numpy::IntoPyArray;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn shape(letter: &str) -> PyResult<BlockShape> {
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => BlockShape::from_letter(c),
+        _ => None,
+    }
+    .ok_or_else(|| value_error(format!("unknown piece '{}'", letter)))
+}
+
+fn letter(shape: BlockShape) -> String {
+    shape.letter().to_string()
+}
+
+fn cells(block: &Block) -> Vec<(i32, i32)> {
+    block
+        .coordinates
+        .iter()
+        .map(|point| (point.get_x(), point.get_y()))
+        .collect()
+}
+
+/// A block's cells as a (4, 2) array of x, y pairs.
+fn cell_array(block: &Block) -> Array2<i32> {
+    Array2::from_shape_fn((block.coordinates.len(), 2), |(i, axis)| {
+        let point = block.coordinates[i];
+        if axis == 0 {
+            point.get_x()
+        } else {
+            point.get_y()
+        }
+    })
+}
+
+/// The filled cells as a (height, width) array of 0s and 1s, top row first.
+fn board_array(board: &Board) -> Array2<u8> {
+    let mut cells = Array2::zeros((board.y_dim as usize, board.x_dim as usize));
+    for point in board.cells.keys() {
+        if point.not_negative() && point.get_y() < board.y_dim && point.get_x() < board.x_dim {
+            cells[(point.get_y() as usize, point.get_x() as usize)] = 1;
+        }
+    }
+    cells
+}
+
+/// A tetromino at a position on a board.
+#[pyclass(name = "Block", module = "tetris")]
+#[derive(Clone)]
+struct PyBlock {
+    block: Block,
+}
+
+#[pymethods]
+impl PyBlock {
+    /// A block of `shape`, a piece letter such as "T", with its origin at
+    /// (`x`, `y`).
+    #[new]
+    #[pyo3(signature = (shape, x=0, y=0))]
+    fn new(shape: &str, x: i32, y: i32) -> PyResult<Self> {
+        Ok(PyBlock {
+            block: Block::new(Point::new(x, y), self::shape(shape)?),
+        })
+    }
+
+    #[getter]
+    fn shape(&self) -> String {
+        letter(self.block.shape)
+    }
+
+    /// The cells the block covers, as (x, y) pairs.
+    #[getter]
+    fn cells(&self) -> Vec<(i32, i32)> {
+        cells(&self.block)
+    }
+
+    /// The block turned a quarter clockwise.
+    fn rotate(&self) -> PyResult<Self> {
+        Ok(PyBlock {
+            block: self.block.rotate().map_err(value_error)?,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Block('{}', {:?})",
+            letter(self.block.shape),
+            cells(&self.block)
+        )
+    }
+}
+
+/// A playfield of locked cells.
+#[pyclass(name = "Board", module = "tetris")]
+#[derive(Clone)]
+struct PyBoard {
+    board: Board,
+}
+
+#[pymethods]
+impl PyBoard {
+    #[new]
+    #[pyo3(signature = (width=10, height=20))]
+    fn new(width: usize, height: usize) -> Self {
+        PyBoard {
+            board: Board::new(width, height),
+        }
+    }
+
+    /// A board from rows of text, top row first, with '.' for empty cells
+    /// and a piece letter or '#' for filled ones.
+    #[staticmethod]
+    fn from_rows(width: usize, height: usize, rows: Vec<String>) -> PyResult<Self> {
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        Ok(PyBoard {
+            board: Board::from_rows(width, height, &rows).map_err(value_error)?,
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> i32 {
+        self.board.x_dim
+    }
+
+    #[getter]
+    fn height(&self) -> i32 {
+        self.board.y_dim
+    }
+
+    /// Every row as text, top row first.
+    fn rows(&self) -> Vec<String> {
+        self.board.rows()
+    }
+
+    /// Whether the cell at (`x`, `y`) is filled.
+    fn filled(&self, x: i32, y: i32) -> bool {
+        self.board.cell(&Point::new(x, y)).is_some()
+    }
+
+    /// Whether `block` lies inside the board without overlapping anything.
+    fn fits(&self, block: &PyBlock) -> bool {
+        block
+            .block
+            .coordinates
+            .iter()
+            .all(|point| point.get_x() >= 0 && point.get_x() < self.board.x_dim)
+            && !self.board.block_touches(&block.block)
+    }
+
+    /// Locks `block` onto the board without clearing lines.
+    fn place(&mut self, block: &PyBlock) {
+        self.board.place_block(&block.block);
+    }
+
+    /// Clears full rows and returns how many there were.
+    fn clear_lines(&mut self) -> i32 {
+        self.board.clear_board()
+    }
+
+    /// The filled cells as a uint8 array of shape (height, width).
+    fn to_numpy<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        board_array(&self.board).into_pyarray(py).into_any()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Board({}x{})\n{}",
+            self.board.x_dim, self.board.y_dim, self.board
+        )
+    }
+}
+
+/// A game played frame by frame.
+#[pyclass(name = "Game", module = "tetris", unsendable)]
+struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    /// A game with settings given as `key=value` pairs, such as
+    /// "mode=sprint seed=7"; missing keys keep their defaults.
+    #[new]
+    #[pyo3(signature = (config=""))]
+    fn new(config: &str) -> PyResult<Self> {
+        let config: GameConfig = config.parse().map_err(value_error)?;
+        config.validate().map_err(value_error)?;
+        Ok(PyGame {
+            game: Game::new(config),
+        })
+    }
+
+    /// Applies an input such as "Left" or "HardDrop" and returns whether it
+    /// did anything.
+    fn apply(&mut self, action: &str) -> PyResult<bool> {
+        let action: Action = action.parse().map_err(value_error)?;
+        Ok(self.game.apply(action))
+    }
+
+    /// Runs the game for `frames` frames.
+    #[pyo3(signature = (frames=1))]
+    fn tick(&mut self, frames: u32) {
+        for _ in 0..frames {
+            if self.game.ended() {
+                break;
+            }
+            self.game.tick();
+        }
+    }
+
+    #[getter]
+    fn board(&self) -> PyBoard {
+        PyBoard {
+            board: self.game.board.clone(),
+        }
+    }
+
+    /// The falling block, or None between pieces.
+    #[getter]
+    fn piece(&self) -> Option<PyBlock> {
+        self.game.active_block().map(|block| PyBlock {
+            block: block.clone(),
+        })
+    }
+
+    #[getter]
+    fn hold(&self) -> Option<String> {
+        self.game.hold.map(letter)
+    }
+
+    #[getter]
+    fn queue(&self) -> Vec<String> {
+        self.game.queue.iter().copied().map(letter).collect()
+    }
+
+    #[getter]
+    fn score(&self) -> i32 {
+        self.game.score
+    }
+
+    #[getter]
+    fn lines(&self) -> i32 {
+        self.game.lines
+    }
+
+    #[getter]
+    fn pieces(&self) -> u32 {
+        self.game.pieces
+    }
+
+    #[getter]
+    fn level(&self) -> u32 {
+        self.game.level
+    }
+
+    #[getter]
+    fn frame(&self) -> u64 {
+        self.game.frame
+    }
+
+    #[getter]
+    fn ended(&self) -> bool {
+        self.game.ended()
+    }
+}
+
+/// The training environment: `reset` starts an episode and `step` returns
+/// the observation, reward, whether the episode is done and an info dict.
+#[pyclass(name = "Env", module = "tetris", unsendable)]
+struct PyEnv {
+    env: crate::env::Env,
+    actions: ActionSpace,
+}
+
+#[pymethods]
+impl PyEnv {
+    /// An environment with game settings as `key=value` pairs, the action
+    /// space, "placement" or "frame", and reward weights by name.
+    #[new]
+    #[pyo3(signature = (config="", action_space="placement", rewards=None))]
+    fn new(
+        config: &str,
+        action_space: &str,
+        rewards: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let config: GameConfig = config.parse().map_err(value_error)?;
+        let actions = match action_space {
+            "placement" => ActionSpace::Placement,
+            "frame" => ActionSpace::Frame,
+            _ => {
+                return Err(value_error(format!(
+                    "unknown action space '{}'",
+                    action_space
+                )))
+            }
+        };
+        let mut weights = Rewards::default();
+        for (key, value) in rewards.into_iter().flatten() {
+            weights
+                .set(&key.extract::<String>()?, value.extract()?)
+                .map_err(value_error)?;
+        }
+        Ok(PyEnv {
+            env: crate::env::Env::new(config, actions, weights).map_err(value_error)?,
+            actions,
+        })
+    }
+
+    /// Starts an episode with the pieces of `seed` and returns the first
+    /// observation.
+    #[pyo3(signature = (seed=0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<Bound<'py, PyDict>> {
+        let observation = self.env.reset(seed);
+        observe(py, &observation)
+    }
+
+    /// Takes an action: a target index for the placement space, or an input
+    /// name or None for the frame space.
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: Option<&Bound<'py, PyAny>>,
+    ) -> PyResult<(Bound<'py, PyDict>, f64, bool, Bound<'py, PyDict>)> {
+        let action = match (self.actions, action) {
+            (ActionSpace::Placement, Some(index)) => EnvAction::Place(index.extract()?),
+            (ActionSpace::Placement, None) => {
+                return Err(value_error("placement actions need a target index"))
+            }
+            (ActionSpace::Frame, Some(input)) => {
+                let input: String = input.extract()?;
+                EnvAction::Frame(Some(input.parse().map_err(value_error)?))
+            }
+            (ActionSpace::Frame, None) => EnvAction::Frame(None),
+        };
+        let (observation, reward, done, info) = self.env.step(action).map_err(value_error)?;
+        Ok((
+            observe(py, &observation)?,
+            reward,
+            done,
+            info_dict(py, &info)?,
+        ))
+    }
+}
+
+/// An observation as a dict: the board as a (height, width) uint8 array, the
+/// piece as a (4, 2) int32 array of x, y pairs, and the targets as an
+/// (n, 4, 2) array beside a bool array of whether each one holds first.
+fn observe<'py>(py: Python<'py>, observation: &Observation) -> PyResult<Bound<'py, PyDict>> {
+    let height = observation.board.len();
+    let width = observation.board.first().map_or(0, Vec::len);
+    let board = Array2::from_shape_fn((height, width), |(y, x)| observation.board[y][x] as u8);
+    let targets = Array3::from_shape_fn((observation.targets.len(), 4, 2), |(i, cell, axis)| {
+        cell_array(&observation.targets[i].block)[(cell, axis)]
+    });
+    let holds = Array1::from_iter(observation.targets.iter().map(|target| target.hold));
+
+    let dict = PyDict::new(py);
+    dict.set_item("board", board.into_pyarray(py))?;
+    dict.set_item(
+        "piece",
+        observation
+            .piece
+            .as_ref()
+            .map(|block| cell_array(block).into_pyarray(py)),
+    )?;
+    dict.set_item(
+        "shape",
+        observation.piece.as_ref().map(|block| letter(block.shape)),
+    )?;
+    dict.set_item("hold", observation.hold.map(letter))?;
+    dict.set_item("can_hold", observation.can_hold)?;
+    let queue: Vec<String> = observation.queue.iter().copied().map(letter).collect();
+    dict.set_item("queue", queue)?;
+    dict.set_item("targets", targets.into_pyarray(py))?;
+    dict.set_item("target_holds", holds.into_pyarray(py))?;
+    Ok(dict)
+}
+
+fn info_dict<'py>(py: Python<'py>, info: &Info) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("lines", info.lines)?;
+    dict.set_item("score", info.score)?;
+    dict.set_item("pieces", info.pieces)?;
+    dict.set_item("frame", info.frame)?;
+    dict.set_item("topped_out", info.topped_out)?;
+    Ok(dict)
+}
+
+#[pymodule]
+fn tetris(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBlock>()?;
+    m.add_class::<PyBoard>()?;
+    m.add_class::<PyGame>()?;
+    m.add_class::<PyEnv>()?;
+    Ok(())
+}
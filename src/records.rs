@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Personal bests, keyed by mode and board size (e.g. `sprint 10x20`).
+///
+/// Stored as a text file with one `key = value` line per record, in the
+/// player's home directory by default.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Records {
+    bests: BTreeMap<String, u64>,
+}
+
+impl Records {
+    /// `~/.tetris_records`, or a file in the working directory when no home
+    /// directory is set.
+    pub fn default_path() -> PathBuf {
+        match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".tetris_records"),
+            None => PathBuf::from(".tetris_records"),
+        }
+    }
+
+    /// Loads records from `path`; a missing file means no records yet.
+    pub fn load(path: &Path) -> Result<Records, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Records::default());
+        }
+        let text = fs::read_to_string(path)?;
+        Ok(Records::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Reads `key = value` lines, skipping anything malformed.
+    pub fn parse(text: &str) -> Records {
+        let bests = text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(key, value)| Some((key.trim().to_string(), value.trim().parse().ok()?)))
+            .collect();
+        Records { bests }
+    }
+
+    pub fn key(mode: &str, width: usize, height: usize) -> String {
+        format!("{} {}x{}", mode, width, height)
+    }
+
+    pub fn best(&self, key: &str) -> Option<u64> {
+        self.bests.get(key).copied()
+    }
+
+    /// Stores `value` if it beats the current record, where lower is better
+    /// (times). Returns whether it was a new record.
+    pub fn submit_lowest(&mut self, key: &str, value: u64) -> bool {
+        self.submit(key, value, |new, old| new < old)
+    }
+
+    /// Stores `value` if it beats the current record, where higher is better
+    /// (scores). Returns whether it was a new record.
+    pub fn submit_highest(&mut self, key: &str, value: u64) -> bool {
+        self.submit(key, value, |new, old| new > old)
+    }
+
+    fn submit(&mut self, key: &str, value: u64, beats: impl Fn(u64, u64) -> bool) -> bool {
+        match self.best(key) {
+            Some(old) if !beats(value, old) => false,
+            _ => {
+                self.bests.insert(key.to_string(), value);
+                true
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Records {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.bests {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submit_keeps_best() {
+        let mut records = Records::default();
+        let key = Records::key("sprint", 10, 20);
+
+        assert!(records.submit_lowest(&key, 3000));
+        assert!(!records.submit_lowest(&key, 3500));
+        assert!(records.submit_lowest(&key, 2800));
+        assert_eq!(records.best(&key), Some(2800));
+
+        // Other board sizes are tracked separately
+        assert_eq!(records.best(&Records::key("sprint", 8, 20)), None);
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let mut records = Records::default();
+        records.submit_lowest("sprint 10x20", 2400);
+        records.submit_highest("ultra 10x20", 52000);
+
+        assert_eq!(Records::parse(&records.to_string()), records);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::game::{Action, Game, GameConfig};
+use crate::puzzle::Puzzle;
+#[cfg(feature = "serde")]
+use crate::schema;
+#[cfg(feature = "serde")]
+use std::error::Error;
+#[cfg(feature = "serde")]
+use std::fmt;
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+/// Playback speeds offered by the replay viewer, as multiples of real time.
+pub const SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// A player action and the frame on which it was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayEvent {
+    pub frame: u64,
+    pub action: Action,
+}
+
+/// A recorded game: the configuration it started from (seed and ruleset),
+/// the puzzle if it was one, every input action and how many frames it
+/// lasted.
+///
+/// On disk it is a versioned JSON document (see [`crate::schema`]), the same
+/// form saves take.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    pub config: GameConfig,
+    pub puzzle: Option<Puzzle>,
+    pub frames: u64,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct ReplayError {
+    message: String,
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for ReplayError {}
+
+#[cfg(feature = "serde")]
+fn invalid(message: &str) -> ReplayError {
+    ReplayError {
+        message: format!("invalid replay: {}", message),
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Replay {
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_replay_string())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Replay::from_replay_str(&text)?)
+    }
+
+    pub fn to_replay_string(&self) -> String {
+        schema::to_json(self).expect("replays always serialize")
+    }
+
+    pub fn from_replay_str(text: &str) -> Result<Replay, ReplayError> {
+        let replay: Replay = schema::read_document(text).map_err(|e| invalid(&e))?;
+        replay.config.validate().map_err(|e| invalid(&e))?;
+        Ok(replay)
+    }
+}
+
+/// Re-simulates a recorded game frame by frame.
+pub struct Playback {
+    pub replay: Replay,
+    pub game: Game,
+    next_event: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        let game = Game::start(replay.config.clone(), replay.puzzle.clone());
+        Playback {
+            replay,
+            game,
+            next_event: 0,
+        }
+    }
+
+    /// Whether nothing is left to play: the recording has run out, or the
+    /// game has ended with no more actions due. An ended game's clock stands
+    /// still, so an undo after topping out is recorded on the frame the game
+    /// ended and brings it back.
+    pub fn finished(&self) -> bool {
+        let due = self
+            .replay
+            .events
+            .get(self.next_event)
+            .is_some_and(|event| event.frame <= self.game.frame);
+        !due && (self.game.frame >= self.replay.frames || self.game.ended())
+    }
+
+    /// Applies the actions recorded for the current frame, then advances one
+    /// frame. Does nothing once the recording has ended.
+    pub fn step(&mut self) {
+        if self.finished() {
+            return;
+        }
+        while let Some(event) = self.replay.events.get(self.next_event) {
+            if event.frame > self.game.frame {
+                break;
+            }
+            self.game.apply(event.action);
+            self.next_event += 1;
+        }
+        if self.game.frame < self.replay.frames {
+            self.game.tick();
+        }
+    }
+
+    /// Moves playback to `frame`. Seeking backwards restarts the simulation
+    /// from the beginning, since the game can only run forwards.
+    pub fn seek(&mut self, frame: u64) {
+        let target = frame.min(self.replay.frames);
+        if target < self.game.frame {
+            self.game = Game::start(self.replay.config.clone(), self.replay.puzzle.clone());
+            self.next_event = 0;
+        }
+        while self.game.frame < target && !self.finished() {
+            self.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played_game() -> Game {
+        let mut game = Game::new(GameConfig {
+            seed: 11,
+            level: 5,
+            ..GameConfig::default()
+        });
+        let actions = [
+            Action::Left,
+            Action::Rotate,
+            Action::Right,
+            Action::SoftDrop,
+        ];
+        for frame in 0..3000u64 {
+            if frame % 7 == 0 {
+                game.apply(actions[(frame / 7) as usize % actions.len()]);
+            }
+            game.tick();
+        }
+        game
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_replay_round_trip() {
+        let mut game = Game::new(GameConfig {
+            seed: 11,
+            undo: true,
+            ..GameConfig::default()
+        });
+        game.apply(Action::HardDrop);
+        game.apply(Action::Undo);
+        game.apply(Action::Redo);
+        let replay = game.replay();
+        assert!(replay.events.iter().any(|e| e.action == Action::Redo));
+        let text = replay.to_replay_string();
+
+        assert!(text.starts_with("{\"version\":1,"));
+        assert_eq!(Replay::from_replay_str(&text).unwrap(), replay);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_puzzle_replay_round_trip() {
+        let puzzle: Puzzle = include_str!("../puzzles/tetris.puzzle").parse().unwrap();
+        let mut game = Game::start(GameConfig::default(), Some(puzzle));
+        game.apply(Action::Right);
+        game.tick();
+        let replay = game.replay();
+
+        let restored = Replay::from_replay_str(&replay.to_replay_string()).unwrap();
+        assert_eq!(restored, replay);
+        let playback = Playback::new(restored);
+        assert_eq!(playback.game.board, game.puzzle.unwrap().board);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_replay_rejects_garbage() {
+        assert!(Replay::from_replay_str("hello").is_err());
+        assert!(Replay::from_replay_str("{\"version\":1,\"data\":{}}").is_err());
+        let error = Replay::from_replay_str("{\"version\":2,\"data\":{}}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid replay: schema version 2 is not supported, expected 1"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_replay_rejects_bad_config() {
+        let replay = Replay {
+            config: GameConfig {
+                width: 0,
+                ..GameConfig::default()
+            },
+            puzzle: None,
+            frames: 0,
+            events: Vec::new(),
+        };
+        let error = Replay::from_replay_str(&replay.to_replay_string()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid replay: board must be at least 4x4, got 0x20"
+        );
+    }
+
+    #[test]
+    fn test_playback_reproduces_game() {
+        let game = played_game();
+        let mut playback = Playback::new(game.replay());
+        while !playback.finished() {
+            playback.step();
+        }
+
+        assert_eq!(playback.game.frame, game.frame);
+        assert_eq!(playback.game.board, game.board);
+        assert_eq!(playback.game.score, game.score);
+    }
+
+    #[test]
+    fn test_playback_seek_backwards() {
+        let mut playback = Playback::new(played_game().replay());
+        playback.seek(1000);
+        let board = playback.game.board.clone();
+
+        playback.seek(2500);
+        playback.seek(1000);
+
+        assert_eq!(playback.game.frame, 1000);
+        assert_eq!(playback.game.board, board);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::block::{Block, BlockShape};
+use crate::board::{Board, Cell};
+use crate::game::{Game, GameConfig};
+use crate::point::Point;
+use crate::puzzle::Puzzle;
+use crate::replay::ReplayEvent;
+use crate::schema;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Saves are versioned JSON documents (see [`schema::to_json`]) of a game's
+/// [`GameState`]:
+///
+/// ```text
+/// {"version":1,"data":{"config":{"mode":"endless","seed":42,...},
+///  "frame":1834,"current_block":{"shape":"T",...},
+///  "filled":[{"x":0,"y":18,"cell":"T"},{"x":0,"y":19,"cell":"#"},...],...}}
+/// ```
+///
+/// The recorded input events are stored too, so a resumed game can still be
+/// saved as a replay of the whole session. The undo history is not saved.
+#[derive(Debug)]
+pub struct SaveError {
+    message: String,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid save: {}", self.message)
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+fn invalid(message: String) -> SaveError {
+    SaveError { message }
+}
+
+/// A filled cell of a game's board. Unlike a [`Board`]'s rows, these reach
+/// above the top row, where a game that topped out left its last piece.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FilledCell {
+    x: i32,
+    y: i32,
+    cell: Cell,
+}
+
+/// Everything a game needs to carry on, field by field: the one form saves
+/// and a game's serde impls share. The random generators are their position
+/// in the streams seeded by the config, and the undo history is left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GameState {
+    config: GameConfig,
+    puzzle: Option<Puzzle>,
+    frame: u64,
+    score: i32,
+    lines: i32,
+    level: u32,
+    pieces: u32,
+    splits: Vec<u64>,
+    combo: i32,
+    best_combo: i32,
+    back_to_back: bool,
+    game_over: bool,
+    finished: bool,
+    current_block: Block,
+    spawned: Block,
+    queue: VecDeque<BlockShape>,
+    hold: Option<BlockShape>,
+    can_hold: bool,
+    filled: Vec<FilledCell>,
+    last_rotated: bool,
+    gravity_enabled: bool,
+    gravity_counter: u32,
+    lock_counter: u32,
+    are: u32,
+    gm_eligible: bool,
+    garbage_added: u32,
+    garbage_hole: i32,
+    rng: u128,
+    garbage_rng: u128,
+    finesse_faults: u32,
+    presses: u32,
+    events: Vec<ReplayEvent>,
+}
+
+impl Game {
+    /// Writes the full game state to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_save_string())?;
+        Ok(())
+    }
+
+    /// Restores a game written by [`Game::save`].
+    pub fn load(path: &Path) -> Result<Game, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Game::from_save_str(&text)?)
+    }
+
+    pub fn to_save_string(&self) -> String {
+        schema::to_json(&self.state()).expect("game states always serialize")
+    }
+
+    pub fn from_save_str(text: &str) -> Result<Game, SaveError> {
+        let state = schema::read_document(text).map_err(invalid)?;
+        Game::from_state(state).map_err(invalid)
+    }
+
+    /// The game as the state a save keeps.
+    pub(crate) fn state(&self) -> GameState {
+        GameState {
+            config: self.config.clone(),
+            puzzle: self.puzzle.clone(),
+            frame: self.frame,
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+            pieces: self.pieces,
+            splits: self.splits.clone(),
+            combo: self.combo,
+            best_combo: self.best_combo,
+            back_to_back: self.back_to_back,
+            game_over: self.game_over,
+            finished: self.finished,
+            current_block: self.current_block.clone(),
+            spawned: self.spawned.clone(),
+            queue: self.queue.clone(),
+            hold: self.hold,
+            can_hold: self.can_hold,
+            filled: self
+                .board
+                .sorted_cells()
+                .into_iter()
+                .map(|(p, cell)| FilledCell {
+                    x: p.get_x(),
+                    y: p.get_y(),
+                    cell,
+                })
+                .collect(),
+            last_rotated: self.last_rotated,
+            gravity_enabled: self.gravity_enabled,
+            gravity_counter: self.gravity_counter,
+            lock_counter: self.lock_counter,
+            are: self.are,
+            gm_eligible: self.gm_eligible,
+            garbage_added: self.garbage_added,
+            garbage_hole: self.garbage_hole,
+            rng: self.rng.get_word_pos(),
+            garbage_rng: self.garbage_rng.get_word_pos(),
+            finesse_faults: self.finesse_faults,
+            presses: self.presses,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Rebuilds a game from its state, checking that the config is playable,
+    /// the blocks are whole and on the board, and no filled cell is further
+    /// off the board than a top-out leaves it.
+    pub(crate) fn from_state(state: GameState) -> Result<Game, String> {
+        let config = state.config;
+        config.validate()?;
+        let (width, height) = (config.width as i32, config.height as i32);
+        for (name, block) in [
+            ("current_block", &state.current_block),
+            ("spawned", &state.spawned),
+        ] {
+            if block.coordinates.len() != 4 {
+                return Err(format!("{} needs 4 cells", name));
+            }
+            if let Some(p) = block
+                .coordinates
+                .iter()
+                .find(|p| !(0..width).contains(&p.get_x()) || !(0..height).contains(&p.get_y()))
+            {
+                return Err(format!(
+                    "{} cell {},{} is off the board",
+                    name,
+                    p.get_x(),
+                    p.get_y()
+                ));
+            }
+        }
+        let mut board = Board::new(config.width, config.height);
+        for FilledCell { x, y, cell } in state.filled {
+            // Cells above the top row are kept: they are how a game topped out
+            if !(0..width).contains(&x) || y >= height {
+                return Err(format!("filled cell {},{} is off the board", x, y));
+            }
+            board.cells.insert(Point::new(x, y), cell);
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        rng.set_word_pos(state.rng);
+        let mut garbage_rng = ChaCha8Rng::seed_from_u64(config.seed);
+        garbage_rng.set_stream(1);
+        garbage_rng.set_word_pos(state.garbage_rng);
+
+        Ok(Game {
+            board,
+            current_block: state.current_block,
+            queue: state.queue,
+            hold: state.hold,
+            can_hold: state.can_hold,
+            score: state.score,
+            lines: state.lines,
+            level: state.level,
+            frame: state.frame,
+            game_over: state.game_over,
+            finished: state.finished,
+            splits: state.splits,
+            pieces: state.pieces,
+            combo: state.combo,
+            best_combo: state.best_combo,
+            back_to_back: state.back_to_back,
+            last_clear: None,
+            last_rotated: state.last_rotated,
+            garbage_added: state.garbage_added,
+            rng,
+            garbage_rng,
+            garbage_hole: state.garbage_hole,
+            gravity_counter: state.gravity_counter,
+            lock_counter: state.lock_counter,
+            are: state.are,
+            gm_eligible: state.gm_eligible,
+            puzzle: state.puzzle,
+            gravity_enabled: state.gravity_enabled,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            piece_start: None,
+            finesse: None,
+            finesse_faults: state.finesse_faults,
+            spawned: state.spawned,
+            presses: state.presses,
+            events: state.events,
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use serde_json::{json, Value};
+
+    fn played_game(frames: u64) -> Game {
+        let mut game = Game::new(GameConfig {
+            seed: 23,
+            level: 8,
+            ..GameConfig::default()
+        });
+        let actions = [Action::Rotate, Action::Left, Action::Hold, Action::Right];
+        for frame in 0..frames {
+            if frame % 9 == 0 {
+                game.apply(actions[(frame / 9) as usize % actions.len()]);
+            }
+            game.tick();
+        }
+        game
+    }
+
+    /// Loads `game`'s save after `edit` has changed its state.
+    fn load_edited(game: &Game, edit: impl FnOnce(&mut Value)) -> Result<Game, SaveError> {
+        let mut document: Value = serde_json::from_str(&game.to_save_string()).unwrap();
+        edit(&mut document["data"]);
+        Game::from_save_str(&document.to_string())
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let game = played_game(1500);
+        let restored = Game::from_save_str(&game.to_save_string()).unwrap();
+
+        assert_eq!(restored.to_save_string(), game.to_save_string());
+    }
+
+    #[test]
+    fn test_resumed_game_continues_identically() {
+        let mut original = played_game(1500);
+        let mut resumed = Game::from_save_str(&original.to_save_string()).unwrap();
+
+        for _ in 0..1500 {
+            original.tick();
+            resumed.tick();
+        }
+
+        assert_eq!(resumed.board, original.board);
+        assert_eq!(resumed.queue, original.queue);
+        assert_eq!(resumed.score, original.score);
+    }
+
+    #[test]
+    fn test_save_keeps_cell_types() {
+        let mut game = Game::new(GameConfig {
+            mode: crate::game::GameMode::Dig,
+            seed: 4,
+            ..GameConfig::default()
+        });
+        game.apply(Action::HardDrop);
+        let restored = Game::from_save_str(&game.to_save_string()).unwrap();
+
+        assert_eq!(restored.board, game.board);
+        assert_eq!(restored.board.garbage_rows(), game.board.garbage_rows());
+        assert_eq!(restored.garbage_added, game.garbage_added);
+    }
+
+    #[test]
+    fn test_save_keeps_puzzle() {
+        let puzzle = include_str!("../puzzles/two-squares.puzzle")
+            .parse()
+            .unwrap();
+        let mut game = Game::start(GameConfig::default(), Some(puzzle));
+        game.apply(Action::HardDrop);
+        let restored = Game::from_save_str(&game.to_save_string()).unwrap();
+
+        assert_eq!(restored.puzzle, game.puzzle);
+        assert_eq!(restored.queue, game.queue);
+        assert_eq!(restored.to_save_string(), game.to_save_string());
+    }
+
+    #[test]
+    fn test_load_rejects_bad_input() {
+        assert!(Game::from_save_str("hello").is_err());
+        assert!(Game::from_save_str(r#"{"version":1,"data":{"frame":3}}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_rejects_other_versions() {
+        let text =
+            played_game(10)
+                .to_save_string()
+                .replacen(r#"{"version":1,"#, r#"{"version":2,"#, 1);
+        let error = Game::from_save_str(&text).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid save: schema version 2 is not supported, expected 1"
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_corrupt_cells() {
+        let game = played_game(10);
+        let load = |edit: fn(&mut Value)| load_edited(&game, edit).err().unwrap().to_string();
+
+        assert_eq!(
+            load(|state| {
+                state["current_block"]["coordinates"]
+                    .as_array_mut()
+                    .unwrap()
+                    .pop();
+            }),
+            "invalid save: current_block needs 4 cells"
+        );
+        assert_eq!(
+            load(|state| state["spawned"]["coordinates"][0] = json!({"x": 1, "y": 20})),
+            "invalid save: spawned cell 1,20 is off the board"
+        );
+        assert_eq!(
+            load(|state| state["filled"] = json!([{"x": 10, "y": 19, "cell": "#"}])),
+            "invalid save: filled cell 10,19 is off the board"
+        );
+        // A topped out game has cells above the board
+        let topped_out = load_edited(&game, |state| {
+            state["filled"] = json!([{"x": 0, "y": -1, "cell": "T"}]);
+        });
+        assert!(topped_out.is_ok());
+    }
+
+    #[test]
+    fn test_load_rejects_bad_config() {
+        let error = load_edited(&played_game(10), |state| {
+            state["config"]["width"] = json!(0)
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid save: board must be at least 4x4, got 0x20"
+        );
+
+        // Another seed would be another game, so there is no default
+        let error = load_edited(&played_game(10), |state| {
+            state["config"].as_object_mut().unwrap().remove("seed");
+        })
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("missing field `seed`"));
+    }
+}
@@ -0,0 +1,318 @@
+//! Serde support, built with the `serde` feature, for handing the engine's
+//! values to other programs as JSON.
+//!
+//! Points, blocks, configs and replays derive their serde impls where they
+//! are defined. The types here are written by hand to keep them stable and
+//! readable: shapes and cells as their letter (`#` for garbage), boards as
+//! rows of text in the form read by [`Board::from_rows`], anything else with
+//! a text form (modes, actions, puzzles) as that text, and games as the
+//! fields of the state a save keeps.
+//!
+//! Documents written by [`to_json`] carry [`VERSION`], and [`from_json`]
+//! refuses any other, so a change to the schema can't be misread. Save files
+//! ([`Game::save`]) and replay files
+//! ([`Replay::save`](crate::replay::Replay::save)) are such documents.
+
+use crate::block::BlockShape;
+use crate::board::{Board, Cell};
+use crate::game::{Action, Game, GameMode, Randomizer};
+use crate::puzzle::Puzzle;
+use crate::save::GameState;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// The version of the schema written by [`to_json`].
+pub const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub struct SchemaError {
+    message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid document: {}", self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+fn invalid(message: String) -> SchemaError {
+    SchemaError { message }
+}
+
+/// A value as a JSON document of the current schema version:
+/// `{"version": 1, "data": ...}`.
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, SchemaError> {
+    serde_json::to_string(&Document {
+        version: VERSION,
+        data: value,
+    })
+    .map_err(|e| invalid(e.to_string()))
+}
+
+/// Reads a document written by [`to_json`], checking its version first.
+pub fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, SchemaError> {
+    read_document(text).map_err(invalid)
+}
+
+/// [`from_json`] for the crate's files, which report errors as their own.
+pub(crate) fn read_document<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    let document: Document<serde_json::Value> =
+        serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if document.version != VERSION {
+        return Err(format!(
+            "schema version {} is not supported, expected {}",
+            document.version, VERSION
+        ));
+    }
+    serde_json::from_value(document.data).map_err(|e| e.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document<T> {
+    version: u32,
+    data: T,
+}
+
+/// Serializes types with a text form as that text.
+macro_rules! as_text {
+    ($($name:ty),*) => {$(
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(D::Error::custom)
+            }
+        }
+    )*};
+}
+
+as_text!(GameMode, Randomizer, Action, Puzzle);
+
+impl Serialize for BlockShape {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(self.letter())
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockShape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let letter = char::deserialize(deserializer)?;
+        BlockShape::from_letter(letter)
+            .ok_or_else(|| D::Error::custom(format!("unknown piece '{}'", letter)))
+    }
+}
+
+/// A board as its size and every row, top row first.
+#[derive(Serialize, Deserialize)]
+struct BoardRows {
+    width: usize,
+    height: usize,
+    rows: Vec<String>,
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BoardRows {
+            width: self.x_dim as usize,
+            height: self.y_dim as usize,
+            rows: self.rows(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let board = BoardRows::deserialize(deserializer)?;
+        let rows: Vec<&str> = board.rows.iter().map(String::as_str).collect();
+        Board::from_rows(board.width, board.height, &rows).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(match self {
+            Cell::Block(shape) => shape.letter(),
+            Cell::Garbage => '#',
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match char::deserialize(deserializer)? {
+            '#' => Ok(Cell::Garbage),
+            letter => BlockShape::from_letter(letter)
+                .map(Cell::Block)
+                .ok_or_else(|| D::Error::custom(format!("unknown cell '{}'", letter))),
+        }
+    }
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.state().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Game::from_state(GameState::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::game::GameConfig;
+    use crate::point::Point;
+    use crate::replay::Replay;
+    use serde_json::json;
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        from_json(&to_json(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_core_types() {
+        assert_eq!(
+            serde_json::to_value(Point::new(3, 4)).unwrap(),
+            json!({"x": 3, "y": 4})
+        );
+        assert_eq!(serde_json::to_value(BlockShape::LRev).unwrap(), json!("J"));
+        assert!(serde_json::from_value::<BlockShape>(json!("X")).is_err());
+        assert_eq!(serde_json::to_value(Cell::Garbage).unwrap(), json!("#"));
+        assert_eq!(
+            round_trip(&Cell::Block(BlockShape::Z)),
+            Cell::Block(BlockShape::Z)
+        );
+        assert!(serde_json::from_value::<Cell>(json!(".")).is_err());
+
+        let block = Block::new(Point::new(4, 0), BlockShape::T);
+        let value = serde_json::to_value(&block).unwrap();
+        assert_eq!(value["shape"], "T");
+        assert_eq!(value["coordinates"][0], json!({"x": 5, "y": 0}));
+        assert_eq!(round_trip(&block), block);
+
+        let board: Board = "....\n.T..\nTTT#".parse().unwrap();
+        assert_eq!(
+            serde_json::to_value(&board).unwrap(),
+            json!({"width": 4, "height": 3, "rows": ["....", ".T..", "TTT#"]})
+        );
+        assert_eq!(round_trip(&board), board);
+    }
+
+    #[test]
+    fn test_configs_and_replays() {
+        let config = GameConfig {
+            mode: GameMode::Sprint,
+            seed: 5,
+            ..GameConfig::default()
+        };
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["mode"], "sprint");
+        assert_eq!(round_trip(&config), config);
+
+        let puzzle: Puzzle = include_str!("../puzzles/tetris.puzzle").parse().unwrap();
+        let mut game = Game::start(config, Some(puzzle));
+        game.apply(Action::Rotate);
+        game.tick();
+        game.apply(Action::HardDrop);
+        game.tick();
+        let replay = game.replay();
+        let value = serde_json::to_value(&replay).unwrap();
+        assert_eq!(value["events"][1]["action"], "HardDrop");
+        assert_eq!(round_trip(&replay), replay);
+        assert_eq!(
+            round_trip::<Replay>(&Replay {
+                puzzle: None,
+                ..replay.clone()
+            })
+            .puzzle,
+            None
+        );
+    }
+
+    #[test]
+    fn test_games_keep_their_state() {
+        let mut game = Game::new(GameConfig {
+            seed: 8,
+            ..GameConfig::default()
+        });
+        for action in [Action::Left, Action::HardDrop, Action::Hold, Action::Right] {
+            game.apply(action);
+            for _ in 0..10 {
+                game.tick();
+            }
+        }
+        game.board.cells.insert(Point::new(0, 19), Cell::Garbage);
+        let value = serde_json::to_value(&game).unwrap();
+        assert_eq!(value["pieces"], 1);
+        assert_eq!(value["hold"], json!(game.hold.unwrap().letter()));
+        assert_eq!(value["queue"].as_array().unwrap().len(), game.queue.len());
+        let filled = value["filled"].as_array().unwrap();
+        assert_eq!(filled.len(), 5);
+        assert!(filled.contains(&json!({"x": 0, "y": 19, "cell": "#"})));
+
+        let mut restored = round_trip(&game);
+        assert_eq!(restored.state(), game.state());
+        // The random generator came along, so the same pieces follow
+        for _ in 0..20 {
+            game.apply(Action::HardDrop);
+            game.tick();
+            restored.apply(Action::HardDrop);
+            restored.tick();
+        }
+        assert_eq!(restored.board, game.board);
+    }
+
+    #[test]
+    fn test_games_are_checked() {
+        let game = Game::new(GameConfig::default());
+        let value = serde_json::to_value(&game).unwrap();
+
+        let mut short = value.clone();
+        short["current_block"]["coordinates"]
+            .as_array_mut()
+            .unwrap()
+            .pop();
+        let error = serde_json::from_value::<Game>(short).err().unwrap();
+        assert_eq!(error.to_string(), "current_block needs 4 cells");
+
+        let mut off = value.clone();
+        off["filled"] = json!([{"x": 10, "y": 19, "cell": "T"}]);
+        let error = serde_json::from_value::<Game>(off).err().unwrap();
+        assert_eq!(error.to_string(), "filled cell 10,19 is off the board");
+
+        // A topped-out game keeps its cells above the board
+        let mut topped = value;
+        topped["filled"] = json!([{"x": 4, "y": -1, "cell": "Z"}]);
+        let game = serde_json::from_value::<Game>(topped).unwrap();
+        assert_eq!(
+            game.board.cell(&Point::new(4, -1)),
+            Some(Cell::Block(BlockShape::ZRev))
+        );
+    }
+
+    #[test]
+    fn test_versions_are_checked() {
+        let document = to_json(&BlockShape::T).unwrap();
+        assert_eq!(document, r#"{"version":1,"data":"T"}"#);
+        let error = from_json::<BlockShape>(r#"{"version":2,"data":"T"}"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid document: schema version 2 is not supported, expected 1"
+        );
+        assert!(from_json::<BlockShape>(r#""T""#).is_err());
+    }
+}
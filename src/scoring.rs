@@ -0,0 +1,333 @@
+use crate::block::{Block, BlockShape};
+use crate::board::Board;
+use crate::point::Point;
+use std::fmt;
+
+/// Whether a lock counted as a T-spin, decided by the three-corner rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// What a single lock achieved, used for scoring and shown to the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clear {
+    pub lines: i32,
+    pub spin: Spin,
+    pub back_to_back: bool,
+    pub combo: i32,
+    pub perfect: bool,
+}
+
+impl Clear {
+    /// Tetrises and T-spins that clear lines keep a back-to-back chain going.
+    pub fn is_difficult(lines: i32, spin: Spin) -> bool {
+        lines > 0 && (lines >= 4 || spin != Spin::None)
+    }
+
+    /// Points for this clear at `level`, following the guideline table:
+    /// 100/300/500/800 for 1-4 lines, 400-1600 for T-spins, 100-400 for
+    /// minis, 1.5x for back-to-back, 50 per combo step and a perfect clear
+    /// bonus.
+    pub fn points(&self, level: u32) -> i32 {
+        let base = match (self.spin, self.lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, _) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        };
+        let base = if self.back_to_back {
+            base * 3 / 2
+        } else {
+            base
+        };
+        let combo = if self.combo > 0 { 50 * self.combo } else { 0 };
+        let perfect = if self.perfect {
+            match self.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ => 2000,
+            }
+        } else {
+            0
+        };
+        (base + combo + perfect) * level as i32
+    }
+
+    /// Points for this clear in master mode, after the arcade formula: the
+    /// level before the clear plus the lines, divided by four and rounded up,
+    /// times the lines, one more than the combo and 4 for a perfect clear.
+    pub fn master_points(&self, level: u32) -> i32 {
+        if self.lines == 0 {
+            return 0;
+        }
+        let bravo = if self.perfect { 4 } else { 1 };
+        (level as i32 + self.lines + 3) / 4 * self.lines * (self.combo + 1) * bravo
+    }
+}
+
+impl fmt::Display for Clear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.back_to_back {
+            parts.push("Back-to-Back".to_string());
+        }
+        match self.spin {
+            Spin::None => {}
+            Spin::Mini => parts.push("T-Spin Mini".to_string()),
+            Spin::Full => parts.push("T-Spin".to_string()),
+        }
+        let name = match self.lines {
+            0 => None,
+            1 => Some("Single"),
+            2 => Some("Double"),
+            3 => Some("Triple"),
+            _ => Some("Tetris"),
+        };
+        parts.extend(name.map(str::to_string));
+        if self.perfect {
+            parts.push("Perfect Clear".to_string());
+        }
+        if self.combo > 0 {
+            parts.push(format!("{} Combo", self.combo));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+const SIDES: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const CORNERS: [(i32, i32); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+/// Classifies a T block about to lock with the three-corner rule: at least
+/// three of the four cells diagonal to its centre must be filled or outside
+/// the board. It is a full T-spin when both corners on the side the T points
+/// to are among them, otherwise a mini.
+///
+/// Callers should only ask when the block's last move was a rotation.
+pub fn t_spin(board: &Board, block: &Block) -> Spin {
+    if block.shape != BlockShape::T {
+        return Spin::None;
+    }
+    let cells = &block.coordinates;
+    let offset = |p: &Point, (dx, dy): (i32, i32)| Point::new(p.get_x() + dx, p.get_y() + dy);
+
+    // The centre is the only cell touching the other three
+    let center = match cells.iter().find(|p| {
+        SIDES
+            .iter()
+            .filter(|&&d| cells.contains(&offset(p, d)))
+            .count()
+            == 3
+    }) {
+        Some(center) => *center,
+        None => return Spin::None,
+    };
+    // The nub is the side cell without a partner opposite it
+    let (nx, ny) = match SIDES.iter().find(|&&(dx, dy)| {
+        cells.contains(&offset(&center, (dx, dy))) && !cells.contains(&offset(&center, (-dx, -dy)))
+    }) {
+        Some(&nub) => nub,
+        None => return Spin::None,
+    };
+
+    let occupied = |p: Point| {
+        p.get_x() < 0
+            || p.get_x() >= board.x_dim
+            || p.get_y() >= board.y_dim
+            || board.cells.contains_key(&p)
+    };
+    let corners: Vec<(i32, i32)> = CORNERS
+        .iter()
+        .copied()
+        .filter(|&c| occupied(offset(&center, c)))
+        .collect();
+    if corners.len() < 3 {
+        return Spin::None;
+    }
+    let front = corners
+        .iter()
+        .filter(|(cx, cy)| cx * nx + cy * ny > 0)
+        .count();
+    if front == 2 {
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}
+
+/// Score needed for each master mode grade from 9 up to S9.
+const GRADE_SCORES: [i32; 18] = [
+    0, 400, 800, 1400, 2000, 3500, 5500, 8000, 12000, 16000, 22000, 30000, 40000, 52000, 66000,
+    82000, 100000, 120000,
+];
+
+/// A master mode grade, counted up from 9 (0) through 1 and S1-S9 to GM (18).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Grade(pub u8);
+
+impl Grade {
+    /// Grand Master, only awarded for finishing with the required times and scores.
+    pub const GM: Grade = Grade(GRADE_SCORES.len() as u8);
+
+    /// The best grade below GM that `score` reaches.
+    pub fn from_score(score: i32) -> Grade {
+        let reached = GRADE_SCORES.iter().filter(|&&s| score >= s).count();
+        Grade(reached.saturating_sub(1) as u8)
+    }
+
+    /// Score needed for the next grade, or `None` at S9 and above.
+    pub fn next_score(&self) -> Option<i32> {
+        GRADE_SCORES.get(self.0 as usize + 1).copied()
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0..=8 => write!(f, "{}", 9 - self.0),
+            9..=17 => write!(f, "S{}", self.0 - 8),
+            _ => write!(f, "GM"),
+        }
+    }
+}
+
+/// Points per cell for soft and hard drops.
+pub const SOFT_DROP_POINTS: i32 = 1;
+pub const HARD_DROP_POINTS: i32 = 2;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: i32, spin: Spin) -> Clear {
+        Clear {
+            lines,
+            spin,
+            back_to_back: false,
+            combo: 0,
+            perfect: false,
+        }
+    }
+
+    /// A T pointing down into a slot whose centre is (1, 2) on a 4x4 board.
+    fn t_in_slot() -> Block {
+        Block {
+            shape: BlockShape::T,
+            coordinates: [
+                Point::new(0, 2),
+                Point::new(1, 2),
+                Point::new(2, 2),
+                Point::new(1, 3),
+            ]
+            .into(),
+        }
+    }
+
+    fn parse_board(text: &str) -> Board {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_t_spin_full() {
+        // The overhang at (2, 1) covers a back corner
+        let board = parse_board(
+            "
+            ....
+            ..#.
+            ...#
+            #.##",
+        );
+
+        assert_eq!(t_spin(&board, &t_in_slot()), Spin::Full);
+    }
+
+    #[test]
+    fn test_t_spin_mini_and_none() {
+        let board = parse_board(
+            "
+            ....
+            #.#.
+            ....
+            #...",
+        );
+        assert_eq!(t_spin(&board, &t_in_slot()), Spin::Mini);
+
+        let board = parse_board(
+            "
+            ....
+            ....
+            ....
+            #.#.",
+        );
+        assert_eq!(t_spin(&board, &t_in_slot()), Spin::None);
+
+        let not_t = Block::new(Point::new(0, 2), BlockShape::L);
+        assert_eq!(t_spin(&board, &not_t), Spin::None);
+    }
+
+    #[test]
+    fn test_line_clear_points() {
+        assert_eq!(clear(1, Spin::None).points(1), 100);
+        assert_eq!(clear(4, Spin::None).points(1), 800);
+        assert_eq!(clear(2, Spin::Full).points(3), 3600);
+        assert_eq!(clear(0, Spin::Mini).points(1), 100);
+    }
+
+    #[test]
+    fn test_bonuses() {
+        let tetris = Clear {
+            back_to_back: true,
+            combo: 2,
+            ..clear(4, Spin::None)
+        };
+        assert_eq!(tetris.points(1), 1200 + 100);
+        assert_eq!(tetris.to_string(), "Back-to-Back Tetris 2 Combo");
+
+        let perfect = Clear {
+            perfect: true,
+            ..clear(2, Spin::None)
+        };
+        assert_eq!(perfect.points(2), (300 + 1200) * 2);
+    }
+
+    #[test]
+    fn test_master_points() {
+        // (97 + 4) / 4 rounded up is 26, times 4 lines
+        assert_eq!(clear(4, Spin::None).master_points(97), 26 * 4);
+        let combo = Clear {
+            combo: 1,
+            perfect: true,
+            ..clear(2, Spin::None)
+        };
+        assert_eq!(combo.master_points(10), 3 * 2 * 2 * 4);
+        assert_eq!(clear(0, Spin::Full).master_points(10), 0);
+    }
+
+    #[test]
+    fn test_grades() {
+        assert_eq!(Grade::from_score(0).to_string(), "9");
+        assert_eq!(Grade::from_score(12000).to_string(), "1");
+        assert_eq!(Grade::from_score(15999).to_string(), "1");
+        assert_eq!(Grade::from_score(16000).to_string(), "S1");
+        assert_eq!(Grade::from_score(500000).to_string(), "S9");
+        assert_eq!(Grade::from_score(500000).next_score(), None);
+        assert_eq!(Grade::GM.to_string(), "GM");
+        assert!(Grade::GM > Grade::from_score(500000));
+    }
+}
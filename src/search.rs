@@ -0,0 +1,301 @@
+use crate::block::Block;
+use crate::board::Board;
+use crate::game::{moved_block, Action};
+use crate::point::Point;
+use arrayvec::ArrayVec;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// A place a block can lock and the inputs that take it there from where it
+/// started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub block: Block,
+    /// Moves ending with a hard drop.
+    pub inputs: Vec<Action>,
+}
+
+/// The search over the positions a block can reach from `start` with the
+/// game's own moves. It finds the cheapest way to each position, counting
+/// steering presses (left, right and rotate) first and soft drops second,
+/// since gravity could do the dropping. That makes tucks under overhangs and
+/// spins into slots reachable as well as plain drops. Under instant (20G)
+/// gravity the block falls to the floor after every move, as it does in the
+/// game, so only moves along the stack are possible.
+struct Search {
+    /// Each visited block and the step that reached it.
+    nodes: Vec<(Block, Option<(usize, Action)>)>,
+    /// The cheapest (steering presses, soft drops) found for each position,
+    /// and the node it was found at.
+    best: HashMap<ArrayVec<Point, 4>, ((u32, u32), usize)>,
+}
+
+impl Search {
+    /// Searches outward from `start` until `stop` accepts a block, or until
+    /// every reachable position has been found. Returns the accepted node.
+    fn run(
+        board: &Board,
+        start: &Block,
+        instant_gravity: bool,
+        mut stop: impl FnMut(&Block) -> bool,
+    ) -> (Search, Option<usize>) {
+        let fall = |block: Block| {
+            if instant_gravity {
+                sonic_drop(board, block)
+            } else {
+                block
+            }
+        };
+        let start = fall(start.clone());
+        let mut search = Search {
+            best: HashMap::from([(cells(&start), ((0, 0), 0))]),
+            nodes: vec![(start, None)],
+        };
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, 0, 0)));
+
+        while let Some(Reverse((steering, drops, index))) = queue.pop() {
+            let block = search.nodes[index].0.clone();
+            if search.best.get(&cells(&block)) != Some(&((steering, drops), index)) {
+                continue;
+            }
+            if stop(&block) {
+                return (search, Some(index));
+            }
+            for action in [
+                Action::Left,
+                Action::Right,
+                Action::Rotate,
+                Action::SoftDrop,
+            ] {
+                let Some(next) = moved_block(board, &block, action).map(fall) else {
+                    continue;
+                };
+                let cost = match action {
+                    Action::SoftDrop => (steering, drops + 1),
+                    _ => (steering + 1, drops),
+                };
+                let key = cells(&next);
+                if search
+                    .best
+                    .get(&key)
+                    .is_some_and(|(known, _)| *known <= cost)
+                {
+                    continue;
+                }
+                search.nodes.push((next, Some((index, action))));
+                let node = search.nodes.len() - 1;
+                search.best.insert(key, (cost, node));
+                queue.push(Reverse((cost.0, cost.1, node)));
+            }
+        }
+        (search, None)
+    }
+
+    /// The actions leading to `index`, with the soft drops straight down onto
+    /// the end replaced by a hard drop.
+    fn inputs(&self, mut index: usize) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Some((parent, action)) = self.nodes[index].1 {
+            actions.push(action);
+            index = parent;
+        }
+        actions.reverse();
+        while actions.last() == Some(&Action::SoftDrop) {
+            actions.pop();
+        }
+        actions.push(Action::HardDrop);
+        actions
+    }
+}
+
+/// Every position `start` can lock in on `board`, each with the fewest
+/// presses that get it there. `instant_gravity` is whether the game's
+/// gravity is 20G (see [`Gravity::is_instant`]).
+///
+/// [`Gravity::is_instant`]: crate::game::Gravity::is_instant
+pub fn routes(board: &Board, start: &Block, instant_gravity: bool) -> Vec<Route> {
+    let (search, _) = Search::run(board, start, instant_gravity, |_| false);
+    let mut grounded: Vec<usize> = search
+        .best
+        .values()
+        .map(|&(_, index)| index)
+        .filter(|&index| moved_block(board, &search.nodes[index].0, Action::SoftDrop).is_none())
+        .collect();
+    // In the order they were found, so the result doesn't depend on hashing
+    grounded.sort();
+    grounded
+        .into_iter()
+        .map(|index| Route {
+            block: search.nodes[index].0.clone(),
+            inputs: search.inputs(index),
+        })
+        .collect()
+}
+
+/// The shortest key sequence that takes `start` to `target` on `board`,
+/// ending with a hard drop, or `None` if the target can't be reached. As in
+/// [`routes`], `instant_gravity` is whether the gravity is 20G.
+pub fn route_to(
+    board: &Board,
+    start: &Block,
+    target: &Block,
+    instant_gravity: bool,
+) -> Option<Vec<Action>> {
+    let goal = cells(target);
+    let (search, found) = Search::run(board, start, instant_gravity, |block| cells(block) == goal);
+    found.map(|index| search.inputs(index))
+}
+
+/// Where `block` comes to rest falling straight down.
+fn sonic_drop(board: &Board, mut block: Block) -> Block {
+    while let Some(next) = moved_block(board, &block, Action::SoftDrop) {
+        block = next;
+    }
+    block
+}
+
+fn cells(block: &Block) -> ArrayVec<Point, 4> {
+    let mut cells = block.coordinates.clone();
+    cells.sort();
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockShape;
+    use crate::finesse::is_steering;
+
+    fn steering(actions: &[Action]) -> usize {
+        actions.iter().filter(|a| is_steering(**a)).count()
+    }
+
+    fn landed(board: &Board, start: &Block, moves: &[Action]) -> Block {
+        let mut block = start.clone();
+        for action in moves {
+            block = moved_block(board, &block, *action).unwrap();
+        }
+        while let Some(next) = moved_block(board, &block, Action::SoftDrop) {
+            block = next;
+        }
+        block
+    }
+
+    fn overhang() -> Board {
+        "
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ..........
+            ......####
+            ..........
+            #.........
+        "
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_straight_drops_need_no_presses() {
+        let board = Board::new(10, 20);
+        let start = Block::new(Point::new(4, 0), BlockShape::T);
+        let target = landed(&board, &start, &[]);
+
+        assert_eq!(
+            route_to(&board, &start, &target, false),
+            Some(vec![Action::HardDrop])
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_to_the_wall() {
+        let board = Board::new(10, 20);
+        let start = Block::new(Point::new(4, 0), BlockShape::L);
+        let moves = [Action::Left; 4];
+        let target = landed(&board, &start, &moves);
+
+        let inputs = route_to(&board, &start, &target, false).unwrap();
+        assert_eq!(steering(&inputs), 4);
+        assert_eq!(landed(&board, &start, &inputs[..inputs.len() - 1]), target);
+    }
+
+    #[test]
+    fn test_rotation_counts_once() {
+        let board = Board::new(10, 20);
+        let start = Block::new(Point::new(4, 0), BlockShape::T);
+        let moves = [Action::Rotate, Action::Right, Action::Rotate, Action::Left];
+        let target = landed(&board, &start, &moves);
+
+        let inputs = route_to(&board, &start, &target, false).unwrap();
+        assert_eq!(steering(&inputs), 2);
+    }
+
+    #[test]
+    fn test_tuck_under_overhang() {
+        let board = overhang();
+        let target = Block {
+            shape: BlockShape::Square,
+            coordinates: [(6, 18), (7, 18), (6, 19), (7, 19)]
+                .iter()
+                .map(|&(x, y)| Point::new(x, y))
+                .collect(),
+        };
+        let start = Block::new(Point::new(4, 0), BlockShape::Square);
+
+        let inputs = route_to(&board, &start, &target, false).unwrap();
+        assert_eq!(steering(&inputs), 2);
+        assert!(inputs.contains(&Action::SoftDrop));
+    }
+
+    #[test]
+    fn test_twenty_g_falls_into_wells() {
+        let board = Board::from_rows(10, 20, &["##..#####."; 10]).unwrap();
+        let start = Block::new(Point::new(4, 0), BlockShape::Square);
+        let shelf = landed(&board, &start, &[Action::Left; 4]);
+
+        // Without gravity the O can pass over the well to the shelf beyond
+        assert!(route_to(&board, &start, &shelf, false).is_some());
+        assert_eq!(route_to(&board, &start, &shelf, true), None);
+        for route in routes(&board, &start, true) {
+            assert!(route.block.coordinates.iter().all(|p| p.get_x() >= 2));
+            assert!(!route.inputs.contains(&Action::SoftDrop));
+        }
+    }
+
+    #[test]
+    fn test_routes_lead_where_they_say() {
+        let board = overhang();
+        let start = Block::new(Point::new(4, 0), BlockShape::T);
+        let routes = routes(&board, &start, false);
+
+        // Tucks under the overhang are found along with the plain drops
+        assert!(routes.iter().any(|route| route
+            .block
+            .coordinates
+            .iter()
+            .all(|p| p.get_y() >= 18 && p.get_x() >= 6)));
+        for route in &routes {
+            let (last, moves) = route.inputs.split_last().unwrap();
+            assert_eq!(*last, Action::HardDrop);
+            assert_eq!(landed(&board, &start, moves), route.block);
+        }
+        let mut positions: Vec<_> = routes.iter().map(|route| cells(&route.block)).collect();
+        positions.sort();
+        positions.dedup();
+        assert_eq!(positions.len(), routes.len());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::bot::{play, Player};
+use crate::game::{Game, GameConfig};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// How a batch of games is run: `games` games with consecutive seeds from
+/// `config.seed`, each stopped after `max_pieces`, spread over `threads`.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub config: GameConfig,
+    pub games: u32,
+    pub max_pieces: u32,
+    pub threads: usize,
+}
+
+/// How one game of a simulation went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Outcome {
+    pub seed: u64,
+    pub pieces: u32,
+    pub lines: i32,
+    pub score: i32,
+    /// The bot didn't top out before the game ended or ran out of pieces.
+    pub survived: bool,
+}
+
+impl Simulation {
+    /// Plays every game, each with a player from `player`, and returns the
+    /// outcomes in seed order. The first game a player fails in stops the run.
+    pub fn run<F>(&self, player: F) -> Result<Vec<Outcome>, Box<dyn Error>>
+    where
+        F: Fn() -> Result<Box<dyn Player>, Box<dyn Error>> + Sync,
+    {
+        let next = AtomicU32::new(0);
+        let outcomes = Mutex::new(Vec::with_capacity(self.games as usize));
+        let threads = self.threads.clamp(1, self.games.max(1) as usize);
+
+        // Players can't cross threads, so each thread makes its own
+        let results: Vec<Result<(), String>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut player = player().map_err(|e| e.to_string())?;
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            if index >= self.games {
+                                return Ok(());
+                            }
+                            let outcome = self
+                                .play_one(player.as_mut(), index)
+                                .map_err(|e| e.to_string())?;
+                            outcomes.lock().unwrap().push(outcome);
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap_or(Err("a game panicked".to_string())))
+                .collect()
+        });
+        results.into_iter().collect::<Result<(), String>>()?;
+
+        let mut outcomes = outcomes.into_inner().unwrap();
+        outcomes.sort_by_key(|outcome| outcome.seed);
+        Ok(outcomes)
+    }
+
+    fn play_one(&self, player: &mut dyn Player, index: u32) -> Result<Outcome, Box<dyn Error>> {
+        let seed = self.config.seed.wrapping_add(index as u64);
+        let mut game = Game::new(GameConfig {
+            seed,
+            undo: false,
+            finesse: false,
+            ..self.config.clone()
+        });
+        play(player, &mut game, self.max_pieces)?;
+        Ok(Outcome {
+            seed,
+            pieces: game.pieces,
+            lines: game.lines,
+            score: game.score,
+            survived: !game.game_over,
+        })
+    }
+}
+
+/// Aggregate statistics over a simulation's games.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    pub games: usize,
+    pub survived: usize,
+    /// The fraction of games survived, from 0 to 1.
+    pub survival: f64,
+    pub mean_lines: f64,
+    pub median_lines: f64,
+    pub mean_pieces: f64,
+    pub median_pieces: f64,
+    pub mean_score: f64,
+    pub median_score: f64,
+}
+
+impl Summary {
+    pub fn of(outcomes: &[Outcome]) -> Summary {
+        let lines: Vec<f64> = outcomes.iter().map(|o| o.lines as f64).collect();
+        let pieces: Vec<f64> = outcomes.iter().map(|o| o.pieces as f64).collect();
+        let scores: Vec<f64> = outcomes.iter().map(|o| o.score as f64).collect();
+        let survived = outcomes.iter().filter(|o| o.survived).count();
+        Summary {
+            games: outcomes.len(),
+            survived,
+            survival: survived as f64 / outcomes.len().max(1) as f64,
+            mean_lines: mean(&lines),
+            median_lines: median(&lines),
+            mean_pieces: mean(&pieces),
+            median_pieces: median(&pieces),
+            mean_score: mean(&scores),
+            median_score: median(&scores),
+        }
+    }
+
+    /// The summary as a CSV header line and a line of values.
+    pub fn csv(&self) -> String {
+        format!(
+            "games,survived,survival,mean_lines,median_lines,mean_pieces,median_pieces,mean_score,median_score\n\
+             {},{},{:.4},{:.2},{},{:.2},{},{:.2},{}\n",
+            self.games,
+            self.survived,
+            self.survival,
+            self.mean_lines,
+            self.median_lines,
+            self.mean_pieces,
+            self.median_pieces,
+            self.mean_score,
+            self.median_score
+        )
+    }
+}
+
+/// The statistics to print: the summary, or every game with `per_game`.
+pub fn report(
+    outcomes: &[Outcome],
+    format: Format,
+    per_game: bool,
+) -> Result<String, Box<dyn Error>> {
+    Ok(match (format, per_game) {
+        (Format::Csv, false) => Summary::of(outcomes).csv(),
+        #[cfg(feature = "serde")]
+        (Format::Json, false) => serde_json::to_string_pretty(&Summary::of(outcomes))? + "\n",
+        (Format::Csv, true) => {
+            let mut csv = "seed,pieces,lines,score,survived\n".to_string();
+            for o in outcomes {
+                csv += &format!(
+                    "{},{},{},{},{}\n",
+                    o.seed, o.pieces, o.lines, o.score, o.survived
+                );
+            }
+            csv
+        }
+        #[cfg(feature = "serde")]
+        (Format::Json, true) => serde_json::to_string_pretty(outcomes)? + "\n",
+        #[cfg(not(feature = "serde"))]
+        (Format::Json, _) => return Err("JSON output needs the serde feature".into()),
+    })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => sorted[n / 2],
+        n => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+    }
+}
+
+/// How simulation statistics are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+
+    fn outcome(seed: u64, lines: i32, survived: bool) -> Outcome {
+        Outcome {
+            seed,
+            pieces: lines as u32 * 3,
+            lines,
+            score: lines * 100,
+            survived,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary::of(&[
+            outcome(1, 10, true),
+            outcome(2, 40, false),
+            outcome(3, 20, true),
+            outcome(4, 50, true),
+        ]);
+        assert_eq!(summary.games, 4);
+        assert_eq!(summary.survived, 3);
+        assert_eq!(summary.survival, 0.75);
+        assert_eq!(summary.mean_lines, 30.0);
+        assert_eq!(summary.median_lines, 30.0);
+        assert_eq!(summary.median_pieces, 90.0);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+
+        let csv = summary.csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("games,survived,survival,mean_lines"));
+        assert!(lines[1].starts_with("4,3,0.7500,30.00,30,"));
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&summary).unwrap();
+            assert_eq!(json["median_score"], 3000.0);
+        }
+    }
+
+    #[test]
+    fn test_threads_play_the_same_games() {
+        let simulation = |threads| Simulation {
+            config: GameConfig {
+                seed: 20,
+                ..GameConfig::default()
+            },
+            games: 5,
+            max_pieces: 40,
+            threads,
+        };
+        let bot = || Ok(Box::new(Bot::default()) as Box<dyn Player>);
+        let parallel = simulation(3).run(bot).unwrap();
+        assert_eq!(parallel, simulation(1).run(bot).unwrap());
+        let seeds: Vec<u64> = parallel.iter().map(|o| o.seed).collect();
+        assert_eq!(seeds, [20, 21, 22, 23, 24]);
+        assert!(parallel.iter().all(|o| o.survived && o.pieces == 40));
+    }
+
+    #[test]
+    fn test_player_errors_stop_the_run() {
+        let simulation = Simulation {
+            config: GameConfig::default(),
+            games: 3,
+            max_pieces: 10,
+            threads: 2,
+        };
+        let error = simulation.run(|| Err("no such bot".into())).unwrap_err();
+        assert_eq!(error.to_string(), "no such bot");
+    }
+
+    #[test]
+    fn test_per_game_report() {
+        let outcomes = [outcome(7, 2, true), outcome(8, 0, false)];
+        assert_eq!(
+            report(&outcomes, Format::Csv, true).unwrap(),
+            "seed,pieces,lines,score,survived\n7,6,2,200,true\n8,0,0,0,false\n"
+        );
+        #[cfg(feature = "serde")]
+        {
+            let json: serde_json::Value =
+                serde_json::from_str(&report(&outcomes, Format::Json, true).unwrap()).unwrap();
+            assert_eq!(json[1]["survived"], false);
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!("JSON".parse(), Ok(Format::Json));
+        assert_eq!(Format::Csv.to_string(), "csv");
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,805 @@
+//! The Tetris Bot Protocol: JSON messages, one per line, between a frontend
+//! that runs the game and a bot process that decides the moves. The game can
+//! be the frontend for an external bot ([`Client`]) or serve the built-in
+//! bot to another frontend ([`serve`]).
+
+use crate::block::{Block, BlockShape};
+use crate::board::{Board, Cell};
+use crate::bot::{Bot, Player};
+use crate::game::{spawn_point, Action, Game, PREVIEW_LENGTH};
+use crate::point::Point;
+use crate::scoring::{t_spin, Spin};
+use crate::search::route_to;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The protocol's board is always this size, counted from the bottom row.
+pub const TBP_WIDTH: i32 = 10;
+pub const TBP_HEIGHT: i32 = 40;
+
+/// How long a bot gets to exit after `quit` before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A piece type, sent as its letter (see [`BlockShape::letter`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece(pub BlockShape);
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(self.0.letter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let letter = char::deserialize(deserializer)?;
+        BlockShape::from_letter(letter)
+            .map(Piece)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown piece '{}'", letter)))
+    }
+}
+
+/// Which way a piece points, turning clockwise from how it spawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+const ORIENTATIONS: [Orientation; 4] = [
+    Orientation::North,
+    Orientation::East,
+    Orientation::South,
+    Orientation::West,
+];
+
+/// Where a piece locks: the position of its centre cell, with `y` counted
+/// up from the bottom row, and its orientation, as in SRS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(rename = "type")]
+    pub piece: Piece,
+    pub orientation: Orientation,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Location {
+    /// The cells around the centre, with `y` pointing up.
+    fn offsets(shape: BlockShape, orientation: Orientation) -> [(i32, i32); 4] {
+        let mut offsets = match shape {
+            BlockShape::Line => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+            BlockShape::Square => [(0, 0), (1, 0), (0, 1), (1, 1)],
+            BlockShape::T => [(-1, 0), (0, 0), (1, 0), (0, 1)],
+            BlockShape::L => [(-1, 0), (0, 0), (1, 0), (1, 1)],
+            BlockShape::LRev => [(-1, 0), (0, 0), (1, 0), (-1, 1)],
+            BlockShape::Z => [(-1, 0), (0, 0), (0, 1), (1, 1)],
+            BlockShape::ZRev => [(-1, 1), (0, 1), (0, 0), (1, 0)],
+        };
+        let turns = ORIENTATIONS
+            .iter()
+            .position(|o| *o == orientation)
+            .unwrap_or(0);
+        for _ in 0..turns {
+            for offset in &mut offsets {
+                *offset = (offset.1, -offset.0);
+            }
+        }
+        offsets
+    }
+
+    /// The block at this location on a board `height` rows high.
+    pub fn block(&self, height: i32) -> Block {
+        let shape = self.piece.0;
+        Block {
+            shape,
+            coordinates: Location::offsets(shape, self.orientation)
+                .iter()
+                .map(|&(dx, dy)| Point::new(self.x + dx, height - 1 - (self.y + dy)))
+                .collect(),
+        }
+    }
+
+    /// Where `block` is on a board `height` rows high. Pieces that look the
+    /// same in several orientations get the first that fits.
+    pub fn of(block: &Block, height: i32) -> Option<Location> {
+        let mut cells = block.coordinates.clone();
+        cells.sort();
+        ORIENTATIONS.iter().find_map(|&orientation| {
+            block.coordinates.iter().find_map(|center| {
+                let location = Location {
+                    piece: Piece(block.shape),
+                    orientation,
+                    x: center.get_x(),
+                    y: height - 1 - center.get_y(),
+                };
+                let mut placed = location.block(height).coordinates;
+                placed.sort();
+                (placed == cells).then_some(location)
+            })
+        })
+    }
+}
+
+/// A placement suggested by the bot or played by the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: Location,
+    pub spin: Spin,
+}
+
+/// The position a bot starts thinking about. `queue` begins with the
+/// current piece; `board` is [`TBP_HEIGHT`] rows of [`TBP_WIDTH`] cells,
+/// bottom row first, each empty or the letter of a piece (`G` for garbage).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Start {
+    pub hold: Option<Piece>,
+    pub queue: Vec<Piece>,
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub board: Vec<Vec<Option<char>>>,
+}
+
+/// Messages from the frontend to the bot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrontendMessage {
+    /// Asks whether the bot can play by the frontend's rules. Only the
+    /// standard rules exist so far.
+    Rules {},
+    Start(Start),
+    Stop,
+    Suggest,
+    Play {
+        #[serde(rename = "move")]
+        mv: Move,
+    },
+    NewPiece {
+        piece: Piece,
+    },
+    Quit,
+}
+
+/// Messages from the bot to the frontend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotMessage {
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    Ready,
+    Error {
+        reason: String,
+    },
+    /// Moves for the current piece or the one hold would give, best first.
+    Suggestion {
+        moves: Vec<Move>,
+    },
+}
+
+#[derive(Debug)]
+pub struct ProtocolError {
+    message: String,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bot protocol error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+fn protocol_error(message: String) -> ProtocolError {
+    ProtocolError { message }
+}
+
+/// `board` as the protocol's rows. Rows above [`TBP_HEIGHT`] are left out.
+fn tbp_board(board: &Board) -> Vec<Vec<Option<char>>> {
+    (0..TBP_HEIGHT)
+        .map(|row| {
+            let y = board.y_dim - 1 - row;
+            (0..board.x_dim)
+                .map(|x| match board.cell(&Point::new(x, y)) {
+                    _ if y < 0 => None,
+                    Some(Cell::Block(shape)) => Some(shape.letter()),
+                    Some(Cell::Garbage) => Some('G'),
+                    None => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The board sent in a `start` message.
+fn board_from_tbp(rows: &[Vec<Option<char>>]) -> Result<Board, ProtocolError> {
+    if rows.len() > TBP_HEIGHT as usize {
+        return Err(protocol_error(format!("the board has {} rows", rows.len())));
+    }
+    let mut board = Board::new(TBP_WIDTH as usize, TBP_HEIGHT as usize);
+    for (row, cells) in rows.iter().enumerate() {
+        if cells.len() != TBP_WIDTH as usize {
+            return Err(protocol_error(format!("a row has {} cells", cells.len())));
+        }
+        for (x, cell) in cells.iter().enumerate() {
+            let Some(letter) = cell else {
+                continue;
+            };
+            let point = Point::new(x as i32, TBP_HEIGHT - 1 - row as i32);
+            let cell = BlockShape::from_letter(*letter).map_or(Cell::Garbage, Cell::Block);
+            board.cells.insert(point, cell);
+        }
+    }
+    Ok(board)
+}
+
+/// The position as the bot sees it, kept by both ends of the protocol.
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    board: Board,
+    hold: Option<BlockShape>,
+    /// The current piece, then the pieces revealed after it.
+    queue: VecDeque<BlockShape>,
+}
+
+impl State {
+    fn from_start(start: &Start) -> Result<State, ProtocolError> {
+        Ok(State {
+            board: board_from_tbp(&start.board)?,
+            hold: start.hold.map(|piece| piece.0),
+            queue: start.queue.iter().map(|piece| piece.0).collect(),
+        })
+    }
+
+    fn start(&self, combo: u32, back_to_back: bool) -> Start {
+        Start {
+            hold: self.hold.map(Piece),
+            queue: self.queue.iter().copied().map(Piece).collect(),
+            combo,
+            back_to_back,
+            board: tbp_board(&self.board),
+        }
+    }
+
+    /// The piece hold would give instead of the current one.
+    fn alternative(&self) -> Option<BlockShape> {
+        self.hold.or_else(|| self.queue.get(1).copied())
+    }
+
+    /// Locks the move's piece and clears full rows. A piece other than the
+    /// current one means hold was used first.
+    fn play(&mut self, mv: &Move) -> Result<(), ProtocolError> {
+        let piece = mv.location.piece.0;
+        let current = *self
+            .queue
+            .front()
+            .ok_or_else(|| protocol_error("a move was played with no pieces left".to_string()))?;
+        if piece != current && self.alternative() != Some(piece) {
+            return Err(protocol_error(format!(
+                "a {} was played but the {} is current",
+                piece.letter(),
+                current.letter()
+            )));
+        }
+        let block = mv.location.block(self.board.y_dim);
+        let inside = block
+            .coordinates
+            .iter()
+            .all(|p| p.get_x() >= 0 && p.get_x() < self.board.x_dim && p.get_y() >= 0);
+        if !inside || self.board.block_touches(&block) {
+            return Err(protocol_error(format!(
+                "the {} doesn't fit at ({}, {})",
+                piece.letter(),
+                mv.location.x,
+                mv.location.y
+            )));
+        }
+        self.queue.pop_front();
+        if piece != current && self.hold.replace(current).is_none() {
+            self.queue.pop_front();
+        }
+        self.board.place_block(&block);
+        self.board.clear_board();
+        Ok(())
+    }
+
+    /// Every placement of the current piece and of the held alternative,
+    /// best first by `bot`'s judgement.
+    fn suggest(&self, bot: &Bot) -> Vec<Move> {
+        let current = self.queue.front().copied();
+        let alternative = self.alternative().filter(|shape| Some(*shape) != current);
+        let mut scored = Vec::new();
+        for shape in current.into_iter().chain(alternative) {
+            let start = Block::new(spawn_point(&self.board), shape);
+            if self.board.block_touches(&start) {
+                continue;
+            }
+            // The protocol says nothing of gravity, so moves are found as if
+            // there were time to slide the piece around at the top
+            for placement in bot.placements(&self.board, &start, false) {
+                let Some(location) = Location::of(&placement.block, self.board.y_dim) else {
+                    continue;
+                };
+                // A T-spin needs the last move before the drop to be the
+                // rotation that put it in place
+                let steps = placement.inputs.len();
+                let spin = match placement.inputs.get(steps.wrapping_sub(2)) {
+                    Some(Action::Rotate) => t_spin(&self.board, &placement.block),
+                    _ => Spin::None,
+                };
+                scored.push((placement.score, Move { location, spin }));
+            }
+        }
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().map(|(_, mv)| mv).collect()
+    }
+}
+
+fn send<T: Serialize>(output: &mut impl Write, message: &T) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer(&mut *output, message)?;
+    writeln!(output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Runs `bot` as a protocol engine, reading the frontend's messages from
+/// `input` and answering on `output` until told to quit or the input ends.
+/// Messages it doesn't know are ignored, as the protocol asks.
+pub fn serve(bot: &Bot, input: impl BufRead, mut output: impl Write) -> Result<(), Box<dyn Error>> {
+    send(
+        &mut output,
+        &BotMessage::Info {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            author: "the tetris authors".to_string(),
+            features: Vec::new(),
+        },
+    )?;
+    let mut state: Option<State> = None;
+    for line in input.lines() {
+        let Ok(message) = serde_json::from_str::<FrontendMessage>(&line?) else {
+            continue;
+        };
+        match message {
+            FrontendMessage::Rules {} => send(&mut output, &BotMessage::Ready)?,
+            FrontendMessage::Start(start) => match State::from_start(&start) {
+                Ok(start) => state = Some(start),
+                Err(error) => send(
+                    &mut output,
+                    &BotMessage::Error {
+                        reason: error.to_string(),
+                    },
+                )?,
+            },
+            FrontendMessage::Stop => state = None,
+            FrontendMessage::Suggest => {
+                let moves = state.as_ref().map(|state| state.suggest(bot));
+                let moves = moves.unwrap_or_default();
+                send(&mut output, &BotMessage::Suggestion { moves })?;
+            }
+            // A move that can't be played leaves the bot with nothing to go
+            // on until the next start
+            FrontendMessage::Play { mv } => {
+                if let Some(current) = &mut state {
+                    if current.play(&mv).is_err() {
+                        state = None;
+                    }
+                }
+            }
+            FrontendMessage::NewPiece { piece } => {
+                if let Some(state) = &mut state {
+                    state.queue.push_back(piece.0);
+                }
+            }
+            FrontendMessage::Quit => break,
+        }
+    }
+    Ok(())
+}
+
+/// An external bot process driven over the protocol, playing our games.
+pub struct Client {
+    child: Option<Child>,
+    input: Box<dyn Write>,
+    output: Box<dyn BufRead>,
+    /// The position the bot was last told about, with its own moves played.
+    view: Option<State>,
+}
+
+impl Client {
+    /// Starts the bot with `command`, a program and its arguments separated
+    /// by spaces, and checks it accepts the standard rules.
+    pub fn spawn(command: &str) -> Result<Client, Box<dyn Error>> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| protocol_error("no bot command given".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| protocol_error(format!("can't start '{}': {}", program, e)))?;
+        let (Some(input), Some(output)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(protocol_error("the bot has no pipes".to_string()).into());
+        };
+        Client::connect(
+            Some(child),
+            Box::new(input),
+            Box::new(BufReader::new(output)),
+        )
+    }
+
+    fn connect(
+        child: Option<Child>,
+        input: Box<dyn Write>,
+        output: Box<dyn BufRead>,
+    ) -> Result<Client, Box<dyn Error>> {
+        let mut client = Client {
+            child,
+            input,
+            output,
+            view: None,
+        };
+        match client.receive()? {
+            BotMessage::Info { .. } => {}
+            message => return Err(unexpected(&message)),
+        }
+        client.send(&FrontendMessage::Rules {})?;
+        match client.receive()? {
+            BotMessage::Ready => Ok(client),
+            BotMessage::Error { reason } => {
+                Err(protocol_error(format!("the bot refused the rules: {}", reason)).into())
+            }
+            message => Err(unexpected(&message)),
+        }
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> Result<(), Box<dyn Error>> {
+        send(&mut self.input, message)
+    }
+
+    /// The bot's next message, skipping lines that aren't one we know.
+    fn receive(&mut self) -> Result<BotMessage, Box<dyn Error>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.output.read_line(&mut line)? == 0 {
+                return Err(protocol_error("the bot stopped answering".to_string()).into());
+            }
+            if let Ok(message) = serde_json::from_str(&line) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Brings the bot up to date with `game`: new pieces if it only missed
+    /// those, otherwise a fresh start.
+    fn sync(&mut self, game: &Game, current: BlockShape) -> Result<(), Box<dyn Error>> {
+        let state = State {
+            board: game.board.clone(),
+            hold: game.hold,
+            queue: std::iter::once(current)
+                .chain(game.queue.iter().take(PREVIEW_LENGTH).copied())
+                .collect(),
+        };
+        let revealed = match &self.view {
+            Some(view)
+                if view.board.rows() == state.board.rows()
+                    && view.hold == state.hold
+                    && state
+                        .queue
+                        .iter()
+                        .take(view.queue.len())
+                        .eq(view.queue.iter()) =>
+            {
+                Some(view.queue.len())
+            }
+            _ => None,
+        };
+        match revealed {
+            Some(known) => {
+                for &shape in state.queue.iter().skip(known) {
+                    self.send(&FrontendMessage::NewPiece {
+                        piece: Piece(shape),
+                    })?;
+                }
+            }
+            None => {
+                if self.view.is_some() {
+                    self.send(&FrontendMessage::Stop)?;
+                }
+                let combo = (game.combo + 1) as u32;
+                self.send(&FrontendMessage::Start(
+                    state.start(combo, game.back_to_back),
+                ))?;
+            }
+        }
+        self.view = Some(state);
+        Ok(())
+    }
+}
+
+fn unexpected(message: &BotMessage) -> Box<dyn Error> {
+    protocol_error(format!("unexpected message from the bot: {:?}", message)).into()
+}
+
+impl Player for Client {
+    /// Asks the bot for its moves and plays the first one our rotation
+    /// system can reach.
+    fn plan(&mut self, game: &Game) -> Result<Vec<Action>, Box<dyn Error>> {
+        if game.board.x_dim != TBP_WIDTH {
+            return Err(protocol_error(format!("bots play on boards {} wide", TBP_WIDTH)).into());
+        }
+        let Some(block) = game.active_block() else {
+            return Ok(Vec::new());
+        };
+        self.sync(game, block.shape)?;
+        self.send(&FrontendMessage::Suggest)?;
+        let moves = match self.receive()? {
+            BotMessage::Suggestion { moves } => moves,
+            message => return Err(unexpected(&message)),
+        };
+        let held = game.hold.or_else(|| game.queue.front().copied());
+        let instant = game.gravity().is_instant();
+        for mv in moves {
+            let target = mv.location.block(game.board.y_dim);
+            if target.coordinates.iter().any(|p| p.get_y() < 0) {
+                continue;
+            }
+            let inputs = if target.shape == block.shape {
+                route_to(&game.board, block, &target, instant)
+            } else if game.can_hold && held == Some(target.shape) {
+                let start = Block::new(spawn_point(&game.board), target.shape);
+                route_to(&game.board, &start, &target, instant)
+                    .map(|inputs| std::iter::once(Action::Hold).chain(inputs).collect())
+            } else {
+                None
+            };
+            let Some(inputs) = inputs else {
+                continue;
+            };
+            self.send(&FrontendMessage::Play { mv })?;
+            if let Some(view) = &mut self.view {
+                view.play(&mv)?;
+            }
+            return Ok(inputs);
+        }
+        Err(protocol_error("none of the bot's moves can be made".to_string()).into())
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        if let Some(child) = &mut self.child {
+            // A bot that ignores quit mustn't hang the game on exit
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+            while let Ok(None) = child.try_wait() {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::play;
+    use crate::game::GameConfig;
+    use std::io::Cursor;
+    use std::net::{TcpListener, TcpStream};
+    use strum::IntoEnumIterator;
+
+    fn sorted(block: &Block) -> Vec<Point> {
+        let mut cells = block.coordinates.to_vec();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn test_locations_round_trip() {
+        for shape in BlockShape::iter() {
+            let mut block = Block::new(Point::new(4, 10), shape);
+            for _ in 0..4 {
+                let location = Location::of(&block, 20).unwrap();
+                assert_eq!(location.piece, Piece(shape));
+                assert_eq!(sorted(&location.block(20)), sorted(&block));
+                block = block.rotate().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_location() {
+        let location = Location {
+            piece: Piece(BlockShape::T),
+            orientation: Orientation::North,
+            x: 4,
+            y: 0,
+        };
+        let cells = [(3, 19), (4, 19), (5, 19), (4, 18)];
+        assert_eq!(
+            sorted(&location.block(20)),
+            sorted(&Block {
+                shape: BlockShape::T,
+                coordinates: cells.iter().map(|&(x, y)| Point::new(x, y)).collect(),
+            })
+        );
+
+        let mv = Move {
+            location,
+            spin: Spin::None,
+        };
+        let json = r#"{"location":{"type":"T","orientation":"north","x":4,"y":0},"spin":"none"}"#;
+        assert_eq!(serde_json::to_string(&mv).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Move>(json).unwrap(), mv);
+    }
+
+    #[test]
+    fn test_messages() {
+        let rules: FrontendMessage =
+            serde_json::from_str(r#"{"type":"rules","randomizer":"seven_bag"}"#).unwrap();
+        assert_eq!(rules, FrontendMessage::Rules {});
+        let piece: FrontendMessage =
+            serde_json::from_str(r#"{"type":"new_piece","piece":"S"}"#).unwrap();
+        assert_eq!(
+            piece,
+            FrontendMessage::NewPiece {
+                piece: Piece(BlockShape::Z)
+            }
+        );
+        assert!(serde_json::from_str::<FrontendMessage>(r#"{"type":"dance"}"#).is_err());
+        assert!(serde_json::from_str::<Piece>(r#""Q""#).is_err());
+        assert_eq!(
+            serde_json::to_string(&BotMessage::Ready).unwrap(),
+            r#"{"type":"ready"}"#
+        );
+    }
+
+    #[test]
+    fn test_boards_round_trip() {
+        let board = Board::from_rows(10, 20, &["..T.......", "####.#####"]).unwrap();
+        let rows = tbp_board(&board);
+        assert_eq!(rows.len(), TBP_HEIGHT as usize);
+        assert_eq!(rows[0][0], Some('G'));
+        assert_eq!(rows[1][2], Some('T'));
+        let tall = board_from_tbp(&rows).unwrap();
+        assert_eq!(tall.rows()[20..], board.rows()[..]);
+    }
+
+    #[test]
+    fn test_play_uses_hold() {
+        let mut state = State {
+            board: Board::new(10, 40),
+            hold: None,
+            queue: [BlockShape::T, BlockShape::Line, BlockShape::Square]
+                .into_iter()
+                .collect(),
+        };
+        let mv = |piece, x| Move {
+            location: Location {
+                piece: Piece(piece),
+                orientation: Orientation::North,
+                x,
+                y: 0,
+            },
+            spin: Spin::None,
+        };
+        // Holding with nothing held puts the T away and plays the I
+        state.play(&mv(BlockShape::Line, 1)).unwrap();
+        assert_eq!(state.hold, Some(BlockShape::T));
+        assert_eq!(state.queue, [BlockShape::Square]);
+        // An L is neither the current piece nor the held one
+        assert!(state.play(&mv(BlockShape::L, 5)).is_err());
+        // Playing the held T holds the O in its place
+        state.play(&mv(BlockShape::T, 6)).unwrap();
+        assert_eq!(state.hold, Some(BlockShape::Square));
+        assert!(state.queue.is_empty());
+        assert_eq!(state.board.cells.len(), 8);
+    }
+
+    #[test]
+    fn test_serve_conversation() {
+        let start = State {
+            board: Board::from_rows(10, 40, &["####.#####"]).unwrap(),
+            hold: None,
+            queue: [BlockShape::Square, BlockShape::Line].into_iter().collect(),
+        }
+        .start(0, false);
+        let input = [
+            "not json".to_string(),
+            serde_json::to_string(&FrontendMessage::Rules {}).unwrap(),
+            serde_json::to_string(&FrontendMessage::Start(start)).unwrap(),
+            serde_json::to_string(&FrontendMessage::Suggest).unwrap(),
+            serde_json::to_string(&FrontendMessage::Quit).unwrap(),
+            serde_json::to_string(&FrontendMessage::Suggest).unwrap(),
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        serve(&Bot::default(), Cursor::new(input), &mut output).unwrap();
+
+        let replies: Vec<BotMessage> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 3);
+        assert!(matches!(replies[0], BotMessage::Info { .. }));
+        assert_eq!(replies[1], BotMessage::Ready);
+        let BotMessage::Suggestion { moves } = &replies[2] else {
+            panic!("expected a suggestion, got {:?}", replies[2]);
+        };
+        // Holding the O for the I clears the line
+        assert_eq!(moves[0].location.piece, Piece(BlockShape::Line));
+        assert_eq!(moves[0].location.orientation, Orientation::East);
+        assert_eq!(moves[0].location.x, 4);
+    }
+
+    #[test]
+    fn test_client_plays_against_the_engine() {
+        // A local socket stands in for the pipes to a bot process
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let to_engine = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (engine_side, _) = listener.accept().unwrap();
+        let engine = thread::spawn(move || {
+            let input = BufReader::new(engine_side.try_clone().unwrap());
+            serve(&Bot::default(), input, engine_side).unwrap()
+        });
+        let mut client = Client::connect(
+            None,
+            Box::new(to_engine.try_clone().unwrap()),
+            Box::new(BufReader::new(to_engine)),
+        )
+        .unwrap();
+
+        let mut game = Game::new(GameConfig {
+            seed: 5,
+            ..GameConfig::default()
+        });
+        play(&mut client, &mut game, 60).unwrap();
+        assert!(!game.ended());
+        assert_eq!(game.pieces, 60);
+        assert!(game.lines >= 15);
+
+        drop(client);
+        engine.join().unwrap();
+    }
+
+    // Needs a command that runs for a while and ignores its input
+    #[cfg(unix)]
+    #[test]
+    fn test_dropping_kills_a_stuck_bot() {
+        let child = Command::new("sleep")
+            .arg("60")
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let client = Client {
+            child: Some(child),
+            input: Box::new(std::io::sink()),
+            output: Box::new(Cursor::new(Vec::new())),
+            view: None,
+        };
+        let start = Instant::now();
+        drop(client);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+use strum_macros::EnumIter;
+use tetris::block::BlockShape;
+use tui::style::{Color, Style};
+
+/// How cells are drawn in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Theme {
+    /// The original look: `.` for empty, `#` for placed and `*` for the falling block.
+    Classic,
+    /// Solid unicode blocks, two characters per cell so cells look square.
+    Blocks,
+    /// Like `Blocks`, with each shape drawn in its own colour.
+    Color,
+}
+
+impl Theme {
+    pub fn empty(&self) -> &'static str {
+        match self {
+            Theme::Classic => ".",
+            Theme::Blocks | Theme::Color => " .",
+        }
+    }
+
+    pub fn filled(&self) -> &'static str {
+        match self {
+            Theme::Classic => "#",
+            Theme::Blocks | Theme::Color => "██",
+        }
+    }
+
+    pub fn active(&self) -> &'static str {
+        match self {
+            Theme::Classic => "*",
+            Theme::Blocks | Theme::Color => "▓▓",
+        }
+    }
+
+    /// Where a suggested placement would go.
+    pub fn ghost(&self) -> &'static str {
+        match self {
+            Theme::Classic => "+",
+            Theme::Blocks | Theme::Color => "░░",
+        }
+    }
+
+    /// The outline of the placement the hint suggests.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Theme::Classic => "o",
+            Theme::Blocks | Theme::Color => "[]",
+        }
+    }
+
+    /// A cell that doesn't belong where it is.
+    pub fn wrong(&self) -> &'static str {
+        match self {
+            Theme::Classic => "x",
+            Theme::Blocks | Theme::Color => "╳╳",
+        }
+    }
+
+    pub fn wrong_style(&self) -> Style {
+        match self {
+            Theme::Color => Style::default().fg(Color::Red),
+            _ => Style::default(),
+        }
+    }
+
+    /// Style of a cell occupied by `shape`, or by garbage when `None`.
+    pub fn style(&self, shape: Option<BlockShape>) -> Style {
+        if *self != Theme::Color {
+            return Style::default();
+        }
+        let color = match shape {
+            None => Color::Gray,
+            Some(BlockShape::Square) => Color::Yellow,
+            Some(BlockShape::Line) => Color::Cyan,
+            Some(BlockShape::T) => Color::Magenta,
+            Some(BlockShape::L) => Color::LightRed,
+            Some(BlockShape::LRev) => Color::Blue,
+            // Z is the S piece and ZRev the Z piece (see BlockShape::letter)
+            Some(BlockShape::Z) => Color::Green,
+            Some(BlockShape::ZRev) => Color::Red,
+        };
+        Style::default().fg(color)
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Theme::Classic => "classic",
+            Theme::Blocks => "blocks",
+            Theme::Color => "color",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Theme::Classic),
+            "blocks" => Ok(Theme::Blocks),
+            "color" | "colour" => Ok(Theme::Color),
+            _ => Err(format!("unknown theme '{}'", s)),
+        }
+    }
+}
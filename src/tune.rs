@@ -0,0 +1,219 @@
+use crate::bot::{Bot, Player, Weights};
+use crate::game::GameConfig;
+use crate::simulate::Simulation;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::cmp::Reverse;
+use std::error::Error;
+
+/// Players in each tournament; the fittest becomes a parent.
+const TOURNAMENT_SIZE: usize = 3;
+
+/// How far a mutation moves a weight, before normalising.
+const MUTATION_STEP: f64 = 0.2;
+
+/// Evolves the bot's weights with a genetic algorithm. Every candidate plays
+/// the same `games` games (consecutive seeds from `config.seed`, at most
+/// `max_pieces` each) and its fitness is the lines it cleared in them.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    pub config: GameConfig,
+    pub population: usize,
+    pub generations: u32,
+    pub games: u32,
+    pub max_pieces: u32,
+    /// The chance that a child has one of its weights nudged.
+    pub mutation: f64,
+    pub threads: usize,
+    /// Seed for the tuner's own random choices.
+    pub seed: u64,
+}
+
+/// A weight set and the lines it cleared over the tuner's games.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub weights: Weights,
+    pub fitness: u64,
+}
+
+impl Tuner {
+    /// Lines cleared by a bot with `weights` over the tuner's games.
+    pub fn fitness(&self, weights: Weights) -> Result<u64, Box<dyn Error>> {
+        let simulation = Simulation {
+            config: self.config.clone(),
+            games: self.games,
+            max_pieces: self.max_pieces,
+            threads: self.threads,
+        };
+        let outcomes = simulation.run(|| Ok(Box::new(Bot { weights }) as Box<dyn Player>))?;
+        Ok(outcomes.iter().map(|o| o.lines.max(0) as u64).sum())
+    }
+
+    /// Evolves a population grown from `start` and random weight sets and
+    /// returns the fittest candidate found. `report` hears about the best
+    /// candidate of each generation, from generation 0, the starting one.
+    ///
+    /// The bot only compares scores, so weight sets are kept at unit length:
+    /// sets pointing the same way play the same.
+    pub fn run(
+        &self,
+        start: Weights,
+        mut report: impl FnMut(u32, &Candidate),
+    ) -> Result<Candidate, Box<dyn Error>> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let size = self.population.max(2);
+        let mut weights = vec![normalized(to_array(&start))];
+        while weights.len() < size {
+            weights.push(normalized([(); 4].map(|_| rng.gen_range(-1.0..1.0))));
+        }
+        let mut population = self.evaluate(weights)?;
+        report(0, &population[0]);
+
+        // The best tenth carries over unchanged, so the best never gets worse
+        let elites = (size / 10).max(1);
+        for generation in 1..=self.generations {
+            let mut children = Vec::with_capacity(size - elites);
+            while children.len() < size - elites {
+                let a = tournament(&population, &mut rng);
+                let b = tournament(&population, &mut rng);
+                let mut child = crossover(a, b);
+                if rng.gen_bool(self.mutation.clamp(0.0, 1.0)) {
+                    let weight = rng.gen_range(0..child.len());
+                    child[weight] += rng.gen_range(-MUTATION_STEP..MUTATION_STEP);
+                    child = normalized(child);
+                }
+                children.push(child);
+            }
+            population.truncate(elites);
+            population.extend(self.evaluate(children)?);
+            population.sort_by_key(|candidate| Reverse(candidate.fitness));
+            report(generation, &population[0]);
+        }
+        Ok(population[0])
+    }
+
+    /// Candidates for `weights`, fittest first.
+    fn evaluate(&self, weights: Vec<[f64; 4]>) -> Result<Vec<Candidate>, Box<dyn Error>> {
+        let mut candidates = weights
+            .into_iter()
+            .map(|weights| {
+                let weights = from_array(weights);
+                Ok(Candidate {
+                    weights,
+                    fitness: self.fitness(weights)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        candidates.sort_by_key(|candidate| Reverse(candidate.fitness));
+        Ok(candidates)
+    }
+}
+
+/// The fittest of a few candidates picked at random.
+fn tournament<'a>(population: &'a [Candidate], rng: &mut ChaCha8Rng) -> &'a Candidate {
+    (0..TOURNAMENT_SIZE)
+        .map(|_| &population[rng.gen_range(0..population.len())])
+        .max_by_key(|candidate| candidate.fitness)
+        .unwrap_or(&population[0])
+}
+
+/// A child between two parents, closer to the fitter one.
+fn crossover(a: &Candidate, b: &Candidate) -> [f64; 4] {
+    let (fa, fb) = match a.fitness + b.fitness {
+        0 => (1.0, 1.0),
+        _ => (a.fitness as f64, b.fitness as f64),
+    };
+    let (a, b) = (to_array(&a.weights), to_array(&b.weights));
+    normalized([0, 1, 2, 3].map(|i| a[i] * fa + b[i] * fb))
+}
+
+fn to_array(weights: &Weights) -> [f64; 4] {
+    [
+        weights.height,
+        weights.lines,
+        weights.holes,
+        weights.bumpiness,
+    ]
+}
+
+fn from_array([height, lines, holes, bumpiness]: [f64; 4]) -> Weights {
+    Weights {
+        height,
+        lines,
+        holes,
+        bumpiness,
+    }
+}
+
+fn normalized(weights: [f64; 4]) -> [f64; 4] {
+    let length = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+    if length == 0.0 {
+        return weights;
+    }
+    weights.map(|w| w / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(weights: &[f64; 4]) -> f64 {
+        weights.iter().map(|w| w * w).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn test_children_stay_unit_length() {
+        let parent = |weights: [f64; 4], fitness| Candidate {
+            weights: from_array(normalized(weights)),
+            fitness,
+        };
+        let a = parent([1.0, 0.0, 0.0, 0.0], 30);
+        let b = parent([0.0, 1.0, 0.0, 0.0], 10);
+        let child = crossover(&a, &b);
+        assert!((length(&child) - 1.0).abs() < 1e-9);
+        // Weighted by fitness, so three parts of the fitter parent to one
+        assert!((child[0] / child[1] - 3.0).abs() < 1e-9);
+
+        let zero = [
+            parent([0.0, 0.0, 1.0, 0.0], 0),
+            parent([0.0, 0.0, 0.0, 1.0], 0),
+        ];
+        let child = crossover(&zero[0], &zero[1]);
+        assert!((child[2] - child[3]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tuning_never_loses_the_best() {
+        let tuner = Tuner {
+            config: GameConfig {
+                seed: 3,
+                ..GameConfig::default()
+            },
+            population: 4,
+            generations: 2,
+            games: 1,
+            max_pieces: 30,
+            mutation: 0.5,
+            threads: 1,
+            seed: 9,
+        };
+        // Weights that like holes
+        let start = Weights {
+            holes: 1.0,
+            ..Weights::default()
+        };
+        let mut bests = Vec::new();
+        let best = tuner
+            .run(start, |generation, candidate| {
+                bests.push((generation, candidate.fitness))
+            })
+            .unwrap();
+
+        assert_eq!(bests.len(), 3);
+        assert!(bests.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(best.fitness, bests[2].1);
+        assert!(best.fitness >= tuner.fitness(start).unwrap());
+        assert!((length(&to_array(&best.weights)) - 1.0).abs() < 1e-9);
+        assert_eq!(tuner.run(start, |_, _| {}).unwrap(), best);
+    }
+}
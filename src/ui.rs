@@ -0,0 +1,881 @@
+use crate::config::Settings;
+use crate::records::Records;
+use crate::theme::Theme;
+use crossterm::event::{Event, KeyCode};
+use crossterm::{event, execute, terminal};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, Stdout};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tetris::block::{Block, BlockShape};
+use tetris::board::{Board, Cell};
+use tetris::bot::{Bot, Player};
+use tetris::game::{
+    frames_to_millis, Action, Game, GameMode, FRAMES_PER_SECOND, MASTER_LEVELS, MAX_LEVEL,
+    PREVIEW_LENGTH, SPRINT_LINES, SPRINT_SPLIT_LINES,
+};
+use tetris::opener::Opener;
+use tetris::point::Point;
+use tetris::replay::{Playback, Replay, SPEEDS};
+use tetris::scoring::Grade;
+use tui::backend::CrosstermBackend;
+use tui::layout::{Constraint, Direction, Layout};
+use tui::style::Modifier;
+use tui::text::{Span, Spans};
+use tui::widgets::{Block as TuiBlock, Borders, Paragraph};
+use tui::{Frame, Terminal};
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+/// Frames between the bot's inputs, slow enough to follow.
+const BOT_INPUT_FRAMES: u64 = 4;
+
+/// Runs an interactive game in the terminal until the player quits. When
+/// `record` is given, the game is saved there as a replay afterwards; when
+/// `save` is given and the player quits mid-game, the game state is written
+/// there so it can be resumed. With an `opener`, the game trains it: the
+/// next placement is suggested on the board and misplaced cells are marked.
+/// With a bot `player`, the bot plays and the person at the terminal
+/// watches. Otherwise `h` shows where `bot` would put each piece.
+pub fn run(
+    game: Game,
+    settings: &Settings,
+    record: Option<&Path>,
+    save: Option<&Path>,
+    opener: Option<&Opener>,
+    player: Option<&mut dyn Player>,
+    bot: &Bot,
+) -> Result<Game, Box<dyn Error>> {
+    let records_path = Records::default_path();
+    let mut records = Records::load(&records_path)?;
+    let game = with_terminal(|terminal| {
+        play(terminal, game, settings, &mut records, opener, player, bot)
+    })?;
+    records.save(&records_path)?;
+    if let Some(path) = record {
+        game.replay().save(path)?;
+    }
+    if let Some(path) = save {
+        if !game.ended() {
+            game.save(path)?;
+        }
+    }
+    Ok(game)
+}
+
+/// Plays back a recorded game with pause, speed, frame-step and seek controls.
+pub fn run_replay(settings: &Settings, replay: Replay) -> Result<(), Box<dyn Error>> {
+    let records = Records::load(&Records::default_path())?;
+    with_terminal(|terminal| watch(terminal, settings, &records, replay))
+}
+
+fn with_terminal<T>(
+    f: impl FnOnce(&mut Term) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    // Setup terminal
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, terminal::EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let result = f(&mut terminal);
+
+    // Restore terminal
+    terminal::disable_raw_mode()?;
+    execute!(terminal.backend_mut(), terminal::LeaveAlternateScreen)?;
+    result
+}
+
+fn play(
+    terminal: &mut Term,
+    mut game: Game,
+    settings: &Settings,
+    records: &mut Records,
+    opener: Option<&Opener>,
+    mut player: Option<&mut dyn Player>,
+    bot: &Bot,
+) -> Result<Game, Box<dyn Error>> {
+    let frame_duration = Duration::from_secs(1) / FRAMES_PER_SECOND;
+    let mut next_frame = Instant::now() + frame_duration;
+
+    let practice = game.config.mode == GameMode::Practice;
+    let mut editor: Option<Editor> = None;
+    let mut trainer = opener.map(Trainer::new);
+    let mut plan: VecDeque<Action> = VecDeque::new();
+    let mut hint: Option<Hint> = None;
+
+    loop {
+        let mut status = match &editor {
+            _ if player.is_some() => "Bot playing  q quit".to_string(),
+            _ if !practice => String::new(),
+            Some(editor) => editor.status(),
+            None => practice_status(&game),
+        };
+        let mut marks = Marks {
+            cursor: editor.as_ref().map(|editor| editor.cursor),
+            ..Marks::default()
+        };
+        if let Some(trainer) = &mut trainer {
+            trainer.update(&game);
+            if editor.is_none() {
+                status = format!("{}\n{}", trainer.status(), status);
+            }
+            marks.target = trainer.target.clone();
+            marks.wrong = trainer.wrong.clone();
+        }
+        if let Some(hint) = &mut hint {
+            hint.update(&game);
+            marks.hint = hint.block.clone().filter(|_| game.active_block().is_some());
+            if editor.is_none() {
+                status = format!("Hint on, h hides it  {}", status);
+            }
+        }
+        terminal.draw(|f| draw_game(f, &game, settings.theme, records, "Score", status, &marks))?;
+
+        // Handle user input until the next frame is due
+        let mut now = Instant::now();
+        while now < next_frame {
+            if event::poll(next_frame - now)? {
+                if let Event::Key(key) = event::read()? {
+                    match (key.code, &mut editor) {
+                        (KeyCode::Char('q') | KeyCode::Char('Q'), _) => return Ok(game),
+                        _ if player.is_some() => {}
+                        (code, Some(current)) => {
+                            if current.handle(code, &mut game) {
+                                editor = None;
+                            }
+                        }
+                        (KeyCode::Char('e'), None) if practice => editor = Some(Editor::new(&game)),
+                        (KeyCode::Char('g'), None) if practice => game.toggle_gravity(),
+                        (KeyCode::Char('h'), None) => {
+                            hint = match hint {
+                                Some(_) => None,
+                                None => Some(Hint::new(*bot)),
+                            }
+                        }
+                        (code, None) => {
+                            if let Some(action) = key_action(code) {
+                                game.apply(action);
+                            }
+                        }
+                    }
+                }
+            }
+            now = Instant::now();
+        }
+        next_frame += frame_duration;
+
+        // The bot plans each piece as it appears and enters the inputs one by
+        // one, planning again if one doesn't go through
+        if let Some(player) = &mut player {
+            if game.active_block().is_none() {
+                plan.clear();
+            } else if game.frame.is_multiple_of(BOT_INPUT_FRAMES) {
+                if plan.is_empty() {
+                    plan.extend(player.plan(&game)?);
+                }
+                if let Some(action) = plan.pop_front() {
+                    if !game.apply(action) {
+                        plan.clear();
+                    }
+                }
+            }
+        }
+
+        // The game waits while the board is being edited
+        if editor.is_none() {
+            game.tick();
+        }
+
+        // Topping out in practice can be undone
+        if game.ended() && !practice {
+            // The bot's games don't count towards personal bests
+            let mut summary = match player {
+                Some(_) => vec![format!("Score: {}  Lines: {}", game.score, game.lines)],
+                None => finish(&game, records),
+            };
+            summary.push(format!(
+                "Finesse faults: {} in {} pieces",
+                game.finesse_faults, game.pieces
+            ));
+            let title = match game.config.mode {
+                _ if game.puzzle.is_some() && game.finished => "Solved!",
+                _ if game.puzzle.is_some() => "Failed",
+                GameMode::Marathon if game.marathon_complete() => "Victory!",
+                GameMode::Master if game.grade() == Grade::GM => "Grand Master!",
+                GameMode::Ultra if game.finished => "Time's up!",
+                _ if game.finished => "Finished!",
+                _ => "Game Over",
+            };
+            game_over_screen(terminal, title, &summary)?;
+            return Ok(game);
+        }
+    }
+}
+
+fn watch(
+    terminal: &mut Term,
+    settings: &Settings,
+    records: &Records,
+    replay: Replay,
+) -> Result<(), Box<dyn Error>> {
+    let mut playback = Playback::new(replay);
+    let frame_duration = Duration::from_secs(1) / FRAMES_PER_SECOND;
+    let mut next_frame = Instant::now() + frame_duration;
+    let mut speed_index = SPEEDS.iter().position(|&s| s == 1.0).unwrap_or(0);
+    let mut paused = false;
+    let mut budget = 0.0;
+    let seek_frames = 5 * FRAMES_PER_SECOND as u64;
+
+    loop {
+        let status = format!(
+            "Replay {:>6}/{} frames  {}x{}\n\
+             space pause  +/- speed  . step  \u{2190}/\u{2192} seek 5s  home restart  q quit",
+            playback.game.frame,
+            playback.replay.frames,
+            SPEEDS[speed_index],
+            if paused { "  [paused]" } else { "" },
+        );
+        terminal.draw(|f| {
+            draw_game(
+                f,
+                &playback.game,
+                settings.theme,
+                records,
+                "Replay",
+                status,
+                &Marks::default(),
+            )
+        })?;
+
+        let mut now = Instant::now();
+        while now < next_frame {
+            if event::poll(next_frame - now)? {
+                if let Event::Key(key) = event::read()? {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Char('Q') => return Ok(()),
+                        KeyCode::Char(' ') => paused = !paused,
+                        KeyCode::Char('+') | KeyCode::Up => {
+                            speed_index = (speed_index + 1).min(SPEEDS.len() - 1)
+                        }
+                        KeyCode::Char('-') | KeyCode::Down => {
+                            speed_index = speed_index.saturating_sub(1)
+                        }
+                        KeyCode::Char('.') => {
+                            paused = true;
+                            playback.step();
+                        }
+                        KeyCode::Right => playback.seek(playback.game.frame + seek_frames),
+                        KeyCode::Left => {
+                            playback.seek(playback.game.frame.saturating_sub(seek_frames))
+                        }
+                        KeyCode::Home => playback.seek(0),
+                        _ => {}
+                    }
+                }
+            }
+            now = Instant::now();
+        }
+        next_frame += frame_duration;
+
+        if !paused {
+            budget += SPEEDS[speed_index];
+            while budget >= 1.0 {
+                playback.step();
+                budget -= 1.0;
+            }
+        }
+    }
+}
+
+/// The practice mode board editor: a cursor for painting cells and the next
+/// pieces typed so far.
+struct Editor {
+    cursor: Point,
+    next: Vec<BlockShape>,
+}
+
+impl Editor {
+    fn new(game: &Game) -> Editor {
+        Editor {
+            cursor: Point::new(game.board.x_dim / 2, game.board.y_dim - 1),
+            next: Vec::new(),
+        }
+    }
+
+    /// Handles a key in the editor. Returns true when the editor is closed,
+    /// after making the typed pieces the next ones.
+    fn handle(&mut self, code: KeyCode, game: &mut Game) -> bool {
+        let (x, y) = (self.cursor.get_x(), self.cursor.get_y());
+        let (width, height) = (game.board.x_dim, game.board.y_dim);
+        match code {
+            KeyCode::Left => self.cursor = Point::new((x - 1).max(0), y),
+            KeyCode::Right => self.cursor = Point::new((x + 1).min(width - 1), y),
+            KeyCode::Up => self.cursor = Point::new(x, (y - 1).max(0)),
+            KeyCode::Down => self.cursor = Point::new(x, (y + 1).min(height - 1)),
+            KeyCode::Char(' ') => {
+                game.toggle_cell(self.cursor);
+            }
+            KeyCode::Backspace => {
+                self.next.pop();
+            }
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('e') => {
+                if !self.next.is_empty() {
+                    game.set_next(&self.next);
+                }
+                return true;
+            }
+            KeyCode::Char(c) => {
+                if let Some(shape) = BlockShape::from_letter(c) {
+                    self.next.push(shape);
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn status(&self) -> String {
+        let next: String = self.next.iter().map(BlockShape::letter).collect();
+        format!(
+            "Editing  next: {}\n\
+             arrows move  space paint  IOTLJSZ choose next  backspace  enter done",
+            next
+        )
+    }
+}
+
+/// The opener trainer's view of the game: where the opener wants the piece
+/// that just appeared, and the cells out of place. Worked out again only when
+/// the board or the piece changes.
+struct Trainer<'a> {
+    opener: &'a Opener,
+    seen: Option<(Board, Block)>,
+    stage: Option<usize>,
+    target: Option<Block>,
+    wrong: Vec<Point>,
+}
+
+impl<'a> Trainer<'a> {
+    fn new(opener: &'a Opener) -> Trainer<'a> {
+        Trainer {
+            opener,
+            seen: None,
+            stage: None,
+            target: None,
+            wrong: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, game: &Game) {
+        let key = (game.board.clone(), game.spawned().clone());
+        if self.seen.as_ref() == Some(&key) {
+            return;
+        }
+        self.stage = self.opener.stage(&game.board);
+        self.target = self.opener.target(&game.board, game.spawned());
+        self.wrong = self.opener.deviations(&game.board);
+        self.seen = Some(key);
+    }
+
+    fn status(&self) -> String {
+        let mut status = match self.stage {
+            None => format!("{}: complete!", self.opener.name),
+            Some(stage) => format!(
+                "{}: stage {}/{}",
+                self.opener.name,
+                stage + 1,
+                self.opener.stages.len()
+            ),
+        };
+        if let (Some(_), None, Some((_, block))) = (self.stage, &self.target, &self.seen) {
+            status += &format!("  hold the {}", block.shape.letter());
+        }
+        if !self.wrong.is_empty() {
+            status += &format!("  {} cells out of place", self.wrong.len());
+        }
+        status
+    }
+}
+
+/// The bot's pick for the piece that just appeared, shown as an outline for
+/// the player to aim at. Worked out again only when the board or the piece
+/// changes, so it stays put while the piece moves.
+struct Hint {
+    bot: Bot,
+    seen: Option<(Board, Block)>,
+    block: Option<Block>,
+}
+
+impl Hint {
+    fn new(bot: Bot) -> Hint {
+        Hint {
+            bot,
+            seen: None,
+            block: None,
+        }
+    }
+
+    fn update(&mut self, game: &Game) {
+        let key = (game.board.clone(), game.spawned().clone());
+        if self.seen.as_ref() == Some(&key) {
+            return;
+        }
+        self.block = self
+            .bot
+            .best(&game.board, game.spawned(), game.gravity().is_instant())
+            .map(|placement| placement.block);
+        self.seen = Some(key);
+    }
+}
+
+fn practice_status(game: &Game) -> String {
+    let gravity = if game.gravity_enabled { "on" } else { "off" };
+    let topped_out = if game.game_over { "Topped out!  " } else { "" };
+    format!(
+        "{}e edit  g gravity ({})  u undo ({})  y redo ({})  q quit",
+        topped_out,
+        gravity,
+        game.undo_depth(),
+        game.redo_depth()
+    )
+}
+
+fn key_action(code: KeyCode) -> Option<Action> {
+    match code {
+        KeyCode::Left => Some(Action::Left),
+        KeyCode::Right => Some(Action::Right),
+        KeyCode::Down => Some(Action::SoftDrop),
+        KeyCode::Char('r') | KeyCode::Up => Some(Action::Rotate),
+        KeyCode::Char(' ') => Some(Action::HardDrop),
+        KeyCode::Char('c') => Some(Action::Hold),
+        KeyCode::Char('u') => Some(Action::Undo),
+        KeyCode::Char('y') => Some(Action::Redo),
+        _ => None,
+    }
+}
+
+/// Updates personal bests for a game that has ended and describes the result.
+fn finish(game: &Game, records: &mut Records) -> Vec<String> {
+    let mut summary = Vec::new();
+    let key = mode_record_key(game);
+    let previous = records.best(&key);
+    if let Some(puzzle) = &game.puzzle {
+        summary.push(puzzle.name.clone());
+        if game.finished {
+            summary.push(format!(
+                "Solved with {} of {} pieces",
+                game.pieces, puzzle.limit
+            ));
+        } else {
+            summary.push(format!("Not solved: {}", puzzle.goal.description()));
+        }
+        return summary;
+    }
+    let new_best = match game.config.mode {
+        GameMode::Sprint if game.finished => {
+            summary.push(format!("Finished in {}", format_time(game.frame)));
+            for (i, split) in game.splits.iter().enumerate() {
+                summary.push(format!(
+                    "  {:>2} lines  {}",
+                    (i as i32 + 1) * SPRINT_SPLIT_LINES,
+                    format_time(*split)
+                ));
+            }
+            records.submit_lowest(&key, game.frame)
+        }
+        GameMode::Dig if game.finished => {
+            summary.push(format!(
+                "Dug {} garbage rows in {}",
+                game.config.garbage_lines,
+                format_time(game.frame)
+            ));
+            summary.push(format!("Pieces: {}", game.pieces));
+            records.submit_lowest(&key, game.frame)
+        }
+        GameMode::Marathon if game.marathon_complete() => {
+            summary.push(format!(
+                "Marathon complete: {} lines in {}",
+                game.lines,
+                format_time(game.frame)
+            ));
+            summary.push(format!("Score: {}  Level: {}", game.score, game.level));
+            records.submit_highest(&key, game.score.max(0) as u64)
+        }
+        GameMode::Master => {
+            summary.push(format!("Grade: {}", game.grade()));
+            summary.push(format!(
+                "Level {} in {}",
+                game.level,
+                format_time(game.frame)
+            ));
+            summary.push(format!("Score: {}", game.score));
+            records.submit_highest(&key, game.grade().0 as u64)
+        }
+        GameMode::Ultra if game.finished => {
+            summary.push(format!("Time's up! Score: {}", game.score));
+            summary.push(format!("Lines: {}", game.lines));
+            summary.push(format!("Pieces per second: {:.2}", pieces_per_second(game)));
+            summary.push(format!("Best combo: {}", game.best_combo));
+            records.submit_highest(&key, game.score.max(0) as u64)
+        }
+        _ => {
+            summary.push(format!("Score: {}  Lines: {}", game.score, game.lines));
+            return summary;
+        }
+    };
+
+    let show = |value: u64| match game.config.mode {
+        GameMode::Sprint | GameMode::Dig => format_time(value),
+        GameMode::Master => Grade(value as u8).to_string(),
+        _ => value.to_string(),
+    };
+    match previous {
+        Some(best) if !new_best => summary.push(format!("Personal best: {}", show(best))),
+        Some(best) => summary.push(format!("New personal best! (was {})", show(best))),
+        None => summary.push("New personal best!".to_string()),
+    }
+    summary
+}
+
+fn pieces_per_second(game: &Game) -> f64 {
+    if game.frame == 0 {
+        return 0.0;
+    }
+    game.pieces as f64 * FRAMES_PER_SECOND as f64 / game.frame as f64
+}
+
+fn game_over_screen(
+    terminal: &mut Term,
+    title: &str,
+    summary: &[String],
+) -> Result<(), Box<dyn Error>> {
+    terminal.draw(|f| {
+        let size = f.size();
+        let text = format!("{}\n\n{} Press 'Q' to quit.", summary.join("\n"), title);
+        let game_over_widget = Paragraph::new(text).block(
+            TuiBlock::default()
+                .borders(Borders::ALL)
+                .title(title.to_string()),
+        );
+        f.render_widget(game_over_widget, size);
+    })?;
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.code == KeyCode::Char('q') || key.code == KeyCode::Char('Q') {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Draws the board and the hold/next panel beside it, with a panel below
+/// showing the score and `status`.
+fn draw_game(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    game: &Game,
+    theme: Theme,
+    records: &Records,
+    title: &str,
+    status: String,
+    marks: &Marks,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(80), // Board
+            Constraint::Percentage(20), // Score
+        ])
+        .split(f.size());
+    let board_width = game.board.x_dim as u16 * theme.empty().chars().count() as u16 + 2;
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(board_width),
+            Constraint::Length(4 * theme.empty().chars().count() as u16 + 4),
+            Constraint::Min(0),
+        ])
+        .split(chunks[0]);
+
+    // Draw the board
+    let board_widget = draw_board(&game.board, game.active_block(), marks, theme);
+    f.render_widget(board_widget, columns[0]);
+
+    // Draw the held and upcoming shapes
+    let mut side = vec![Spans::from("Hold")];
+    match game.hold {
+        Some(shape) => side.extend(shape_lines(shape, theme)),
+        None => side.extend([Spans::default(), Spans::default()]),
+    }
+    side.push(Spans::default());
+    side.push(Spans::from("Next"));
+    for shape in game.queue.iter().take(PREVIEW_LENGTH) {
+        side.extend(shape_lines(*shape, theme));
+        side.push(Spans::default());
+    }
+    let side_widget = Paragraph::new(side).block(TuiBlock::default().borders(Borders::ALL));
+    f.render_widget(side_widget, columns[1]);
+
+    // Draw the timer and mode progress
+    let mode = match game.puzzle {
+        Some(_) => "puzzle".to_string(),
+        None => game.config.mode.to_string(),
+    };
+    let info_widget = Paragraph::new(info_lines(game, records))
+        .block(TuiBlock::default().borders(Borders::ALL).title(mode));
+    f.render_widget(info_widget, columns[2]);
+
+    // Draw the score
+    let last_clear = game
+        .last_clear
+        .map(|clear| clear.to_string())
+        .unwrap_or_default();
+    let finesse = match game.finesse {
+        Some(finesse) if finesse.is_fault() => format!(
+            "  Finesse fault: {} presses, {} needed",
+            finesse.presses, finesse.minimum
+        ),
+        _ => String::new(),
+    };
+    let score_widget = Paragraph::new(format!(
+        "Score: {}  Lines: {}  Level: {}  Faults: {}  {}{}\n{}",
+        game.score, game.lines, game.level, game.finesse_faults, last_clear, finesse, status
+    ))
+    .block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title(title.to_string()),
+    );
+    f.render_widget(score_widget, chunks[1]);
+}
+
+/// Mode-specific progress shown beside the board.
+fn info_lines<'a>(game: &Game, records: &Records) -> Vec<Spans<'a>> {
+    let mut lines = vec![Spans::from(format!("Time  {}", format_time(game.frame)))];
+    if let Some(puzzle) = &game.puzzle {
+        lines.push(Spans::from(puzzle.name.clone()));
+        lines.push(Spans::from(puzzle.goal.description()));
+        lines.push(Spans::from(format!(
+            "Pieces {}/{}",
+            game.pieces, puzzle.limit
+        )));
+        return lines;
+    }
+    if game.config.mode == GameMode::Sprint {
+        lines.push(Spans::from(format!(
+            "Lines {}/{}",
+            game.lines.min(SPRINT_LINES),
+            SPRINT_LINES
+        )));
+        lines.push(Spans::default());
+        for (i, split) in game.splits.iter().enumerate() {
+            lines.push(Spans::from(format!(
+                "{:>2}    {}",
+                (i as i32 + 1) * SPRINT_SPLIT_LINES,
+                format_time(*split)
+            )));
+        }
+        if let Some(best) = records.best(&mode_record_key(game)) {
+            lines.push(Spans::default());
+            lines.push(Spans::from(format!("Best  {}", format_time(best))));
+        }
+    }
+    if game.config.mode == GameMode::Dig {
+        lines.push(Spans::from(format!(
+            "Dug   {}/{}",
+            game.garbage_cleared(),
+            game.config.garbage_lines
+        )));
+        lines.push(Spans::from(format!("Blocks {}", game.pieces)));
+        if let Some(best) = records.best(&mode_record_key(game)) {
+            lines.push(Spans::default());
+            lines.push(Spans::from(format!("Best  {}", format_time(best))));
+        }
+    }
+    if game.config.mode == GameMode::Marathon {
+        lines.push(Spans::from(format!("Level {}/{}", game.level, MAX_LEVEL)));
+        lines.push(Spans::from(format!(
+            "Lines {}/{}",
+            game.lines, game.config.marathon_lines
+        )));
+        if game.marathon_complete() {
+            lines.push(Spans::from("Goal reached!"));
+        }
+        if let Some(best) = records.best(&mode_record_key(game)) {
+            lines.push(Spans::default());
+            lines.push(Spans::from(format!("Best  {}", best)));
+        }
+    }
+    if game.config.mode == GameMode::Master {
+        let grade = game.grade();
+        lines.push(Spans::from(format!("Grade {}", grade)));
+        if let Some(next) = grade.next_score() {
+            lines.push(Spans::from(format!("Next  {}", next)));
+        }
+        let section_end = (game.level / 100 + 1) * 100;
+        lines.push(Spans::from(format!(
+            "Level {}/{}",
+            game.level,
+            section_end.min(MASTER_LEVELS)
+        )));
+        lines.push(Spans::default());
+        for (i, split) in game.splits.iter().enumerate() {
+            lines.push(Spans::from(format!(
+                "{:>3}   {}",
+                (i + 1) * 100,
+                format_time(*split)
+            )));
+        }
+        if let Some(best) = records.best(&mode_record_key(game)) {
+            lines.push(Spans::default());
+            lines.push(Spans::from(format!("Best  {}", Grade(best as u8))));
+        }
+    }
+    if game.config.mode == GameMode::Ultra {
+        let limit = game.config.time_limit as u64 * FRAMES_PER_SECOND as u64;
+        lines.push(Spans::from(format!(
+            "Left  {}",
+            format_time(limit.saturating_sub(game.frame))
+        )));
+        lines.push(Spans::from(format!("PPS   {:.2}", pieces_per_second(game))));
+        lines.push(Spans::from(format!("Combo {}", game.combo.max(0))));
+        if let Some(best) = records.best(&mode_record_key(game)) {
+            lines.push(Spans::default());
+            lines.push(Spans::from(format!("Best  {}", best)));
+        }
+    }
+    lines
+}
+
+/// Personal bests are kept per mode and board size, and per time limit in
+/// ultra mode or goal in dig and marathon mode.
+fn mode_record_key(game: &Game) -> String {
+    let mode = match game.config.mode {
+        GameMode::Ultra => format!("ultra-{}s", game.config.time_limit),
+        GameMode::Dig => format!("dig-{}", game.config.garbage_lines),
+        GameMode::Marathon => format!("marathon-{}", game.config.marathon_lines),
+        mode => mode.to_string(),
+    };
+    Records::key(&mode, game.config.width, game.config.height)
+}
+
+/// Formats a frame count as `m:ss.mmm`.
+fn format_time(frames: u64) -> String {
+    let millis = frames_to_millis(frames);
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Renders a shape in its spawn orientation as a few lines of cells.
+fn shape_lines<'a>(shape: BlockShape, theme: Theme) -> Vec<Spans<'a>> {
+    let block = Block::new(Point::new(0, 0), shape);
+    let height = block
+        .coordinates
+        .iter()
+        .map(|p| p.get_y())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let width = block
+        .coordinates
+        .iter()
+        .map(|p| p.get_x())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let blank = " ".repeat(theme.empty().chars().count());
+
+    (0..height)
+        .map(|y| {
+            let cells = (0..width)
+                .map(|x| {
+                    if block.coordinates.contains(&Point::new(x, y)) {
+                        Span::styled(theme.active(), theme.style(Some(shape)))
+                    } else {
+                        Span::raw(blank.clone())
+                    }
+                })
+                .collect::<Vec<_>>();
+            Spans::from(cells)
+        })
+        .collect()
+}
+
+/// Cells drawn over the board: the editor cursor, the opener trainer's
+/// suggested placement and misplaced cells, and the hint.
+#[derive(Default)]
+struct Marks {
+    cursor: Option<Point>,
+    target: Option<Block>,
+    wrong: Vec<Point>,
+    hint: Option<Block>,
+}
+
+fn draw_board<'a>(
+    board: &Board,
+    current_block: Option<&Block>,
+    marks: &Marks,
+    theme: Theme,
+) -> Paragraph<'a> {
+    let mut grid = vec![vec![Span::raw(theme.empty()); board.x_dim as usize]; board.y_dim as usize];
+
+    // Mark filled points, coloured by the shape that placed them
+    for (point, cell) in &board.cells {
+        if point.get_x() >= 0 && point.get_y() >= 0 {
+            let shape = match cell {
+                Cell::Block(shape) => Some(*shape),
+                Cell::Garbage => None,
+            };
+            grid[point.get_y() as usize][point.get_x() as usize] =
+                Span::styled(theme.filled(), theme.style(shape));
+        }
+    }
+
+    // Mark misplaced cells and the suggested placements, under the current block
+    for point in &marks.wrong {
+        grid[point.get_y() as usize][point.get_x() as usize] =
+            Span::styled(theme.wrong(), theme.wrong_style());
+    }
+    if let Some(block) = &marks.target {
+        for point in &block.coordinates {
+            grid[point.get_y() as usize][point.get_x() as usize] =
+                Span::styled(theme.ghost(), theme.style(Some(block.shape)));
+        }
+    }
+    if let Some(block) = &marks.hint {
+        for point in &block.coordinates {
+            grid[point.get_y() as usize][point.get_x() as usize] =
+                Span::styled(theme.hint(), theme.style(Some(block.shape)));
+        }
+    }
+
+    // Mark current block
+    if let Some(block) = current_block {
+        for point in &block.coordinates {
+            if point.get_x() >= 0 && point.get_y() >= 0 && point.get_y() < board.y_dim {
+                grid[point.get_y() as usize][point.get_x() as usize] =
+                    Span::styled(theme.active(), theme.style(Some(block.shape)));
+            }
+        }
+    }
+
+    // Highlight the editor cursor
+    if let Some(point) = marks.cursor {
+        let cell = &mut grid[point.get_y() as usize][point.get_x() as usize];
+        *cell = Span::styled(
+            cell.content.clone(),
+            cell.style.add_modifier(Modifier::REVERSED),
+        );
+    }
+
+    // Render the board into lines of styled cells
+    let lines = grid.into_iter().map(Spans::from).collect::<Vec<_>>();
+
+    Paragraph::new(lines).block(TuiBlock::default().borders(Borders::ALL).title("Board"))
+}